@@ -3,19 +3,38 @@ use anyhow::{Ok, Result};
 use clap::Parser;
 use dialoguer::{theme::ColorfulTheme, Input, MultiSelect};
 use rust_xlearn::{
-    cli::*, process_error, DiffConfig, DiffProfile, ExtraArgs, LoadConfig, RequestProfile,
-    ResponseProfile,
+    cli::*, load_data, parse_curl, parse_log, process_error, CookieJar, DiffConfig, DiffProfile,
+    ExtraArgs, Har, LoadConfig, OpenApi, Proxy, Replay, ReqConfig, RequestProfile, ResponseProfile,
 };
 use std::io::Write;
+use std::sync::Arc;
 
 #[tokio::main]
 pub async fn main() -> Result<()> {
-    let args = Args::parse();
+    let args = Args::<DiffRunArgs>::parse();
 
     let result = match args.action {
         Action::Run(args) => run(args).await,
         Action::Parse => parse().await,
-        _ => panic!("Not implemented"),
+        Action::Replay(args) => {
+            // differing or failed requests exit with 1, for CI
+            let result = replay(args).await;
+            if result.is_err() {
+                process_error(result)?;
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Action::Proxy(args) => proxy(args).await,
+        Action::Import(ImportSource::Har(args)) => import_har(args).await,
+        Action::Import(ImportSource::Curl(args)) => import_curl(args),
+        Action::Import(ImportSource::Openapi(args)) => import_openapi(args),
+        Action::Export(_) => Err(anyhow::anyhow!("export is only for xreq")),
+        Action::Test(_) => Err(anyhow::anyhow!("test is only for xreq")),
+        Action::Import(ImportSource::Postman(_) | ImportSource::Insomnia(_)) => {
+            Err(anyhow::anyhow!("collection import is only for xreq"))
+        }
+        _ => Err(anyhow::anyhow!("not implemented")),
     };
 
     process_error(result)
@@ -24,10 +43,10 @@ pub async fn main() -> Result<()> {
 async fn parse() -> Result<()> {
     let theme = ColorfulTheme::default();
     let url1: String = Input::with_theme(&theme)
-        .with_prompt("Url1")
+        .with_prompt("Url1 or curl command")
         .interact_text()?;
     let url2: String = Input::with_theme(&theme)
-        .with_prompt("Url2")
+        .with_prompt("Url2 or curl command")
         .interact_text()?;
     let req1: RequestProfile = url1.parse()?;
     let req2 = url2.parse()?;
@@ -65,13 +84,138 @@ async fn parse() -> Result<()> {
     Ok(())
 }
 
-async fn run(args: RunArgs) -> Result<()> {
+async fn run(args: RunArgs<DiffRunArgs>) -> Result<()> {
+    if let Some(har) = args.tool.har.clone() {
+        return run_har(&har, args).await;
+    }
+    let profile_name = args
+        .profile
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("--profile is required"))?;
     let config_file = args.config.unwrap_or_else(|| "./dif.yml".to_string());
     let config = DiffConfig::load_yaml(&config_file).await?;
-    let profile = config
-        .get_profile(&args.profile)
-        .ok_or_else(|| anyhow::anyhow!("no profile {} in config {}", args.profile, config_file))?;
     let extra_args: ExtraArgs = args.extra_params.into();
-    profile.diff(extra_args).await?;
+    let jar = match args.cookie_jar.as_ref() {
+        Some(path) => Some(Arc::new(CookieJar::load(path)?)),
+        None => None,
+    };
+    let rows = match args.data.as_ref() {
+        Some(path) => load_data(path)?,
+        None => vec![],
+    };
+    match config.get_scenario(&profile_name) {
+        Some(_) if args.data.is_some() => {
+            return Err(anyhow::anyhow!("--data is not supported for scenarios"))
+        }
+        Some(scenario) => scenario.diff_with(extra_args, jar.clone()).await?,
+        None => {
+            let profile = config.get_profile(&profile_name).ok_or_else(|| {
+                anyhow::anyhow!("no profile {} in config {}", profile_name, config_file)
+            })?;
+            profile.diff_data(extra_args, &rows, jar.clone()).await?
+        }
+    };
+    if let (Some(path), Some(jar)) = (args.cookie_jar, jar) {
+        jar.save(path)?;
+    }
+    Ok(())
+}
+
+async fn replay(args: ReplayArgs) -> Result<()> {
+    if !(args.sample > 0.0 && args.sample <= 1.0) {
+        return Err(anyhow::anyhow!("--sample must be in (0, 1]"));
+    }
+    if args.rate.is_some_and(|r| r <= 0.0) {
+        return Err(anyhow::anyhow!("--rate must be positive"));
+    }
+    let content = tokio::fs::read_to_string(&args.log).await?;
+    let (entries, skipped) = parse_log(&content, args.format);
+    if skipped > 0 {
+        eprintln!("skipped {} unparsable lines", skipped);
+    }
+    let replay = Replay {
+        target1: args.target1,
+        target2: args.target2,
+        methods: args.methods,
+        sample: args.sample,
+        rate: args.rate,
+        response: ResponseProfile::new(args.skip_headers, vec![]),
+    };
+    replay.run(entries).await?;
+    Ok(())
+}
+
+async fn proxy(args: ProxyArgs) -> Result<()> {
+    let mut response = match (args.config.as_ref(), args.profile.as_ref()) {
+        (Some(config_file), Some(name)) => {
+            let config = DiffConfig::load_yaml(config_file).await?;
+            let profile = config
+                .get_profile(name)
+                .ok_or_else(|| anyhow::anyhow!("no profile {} in config {}", name, config_file))?;
+            profile.response.clone()
+        }
+        (None, None) => ResponseProfile::default(),
+        _ => return Err(anyhow::anyhow!("--config and --profile go together")),
+    };
+    response.skip_headers.extend(args.skip_headers);
+
+    let proxy = Proxy::new(args.primary, args.candidate, response, args.report)?;
+    let (addr, server) = proxy.bind(args.listen)?;
+    eprintln!("listening on http://{}", addr);
+    server.await
+}
+
+async fn run_har(path: &str, args: RunArgs<DiffRunArgs>) -> Result<()> {
+    let har: Har = tokio::fs::read_to_string(path).await?.parse()?;
+    let response = match args.profile.as_ref() {
+        Some(name) => {
+            let config_file = args.config.unwrap_or_else(|| "./dif.yml".to_string());
+            let config = DiffConfig::load_yaml(&config_file).await?;
+            let profile = config
+                .get_profile(name)
+                .ok_or_else(|| anyhow::anyhow!("no profile {} in config {}", name, config_file))?;
+            profile.response.clone()
+        }
+        None => ResponseProfile::default(),
+    };
+    let extra_args: ExtraArgs = args.extra_params.into();
+    har.diff(&extra_args, &response, &args.tool.methods).await?;
+    Ok(())
+}
+
+async fn import_har(args: ImportHarArgs) -> Result<()> {
+    let har: Har = tokio::fs::read_to_string(&args.path).await?.parse()?;
+    let result = match args.xreq {
+        true => serde_yaml::to_string(&har.to_req_config()?)?,
+        false => serde_yaml::to_string(&har.to_diff_config()?)?,
+    };
+    write!(std::io::stdout().lock(), "{}", result)?;
+    Ok(())
+}
+
+fn import_curl(args: ImportCurlArgs) -> Result<()> {
+    let req: RequestProfile = parse_curl(&args.command)?;
+    let result = match args.xreq {
+        true => serde_yaml::to_string(&ReqConfig::new(
+            vec![(args.profile, req)].into_iter().collect(),
+        ))?,
+        false => {
+            let profile = DiffProfile::new(req.clone(), req, ResponseProfile::default());
+            serde_yaml::to_string(&DiffConfig::new(
+                vec![(args.profile, profile)].into_iter().collect(),
+            ))?
+        }
+    };
+    write!(std::io::stdout().lock(), "{}", result)?;
+    Ok(())
+}
+
+fn import_openapi(args: ImportOpenapiArgs) -> Result<()> {
+    let spec = OpenApi::load(&args.path)?;
+    let (config, skipped) = spec.to_diff_yaml(&args.base1, &args.base2, &args.methods)?;
+    for reason in skipped {
+        eprintln!("skipped {}", reason);
+    }
+    write!(std::io::stdout().lock(), "{}", config)?;
     Ok(())
 }
@@ -2,21 +2,43 @@ use anyhow::{Ok, Result};
 use clap::Parser;
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::Input;
+use reqwest::Response;
 use rust_xlearn::{
-    cli::*, get_body_text, get_header_text, get_status_text, highlight_text, process_error,
-    ExtraArgs, LoadConfig, ReqConfig,
+    cli::*, get_filtered_body_text, get_header_text, get_status_text, highlight_text, load_data,
+    parse_curl, process_error, CookieJar, ExtraArgs, Har, InsomniaExport, Jq, LoadConfig,
+    PostmanCollection, ReqConfig, ResponseProfile, Variables,
 };
 use std::fmt::Write as _;
 use std::io::Write as _;
+use std::sync::Arc;
 
 #[tokio::main]
 pub async fn main() -> Result<()> {
-    let args = Args::parse();
+    let args = Args::<ReqRunArgs>::parse();
 
     let result = match args.action {
         Action::Run(args) => run(args).await,
         Action::Parse => parse().await,
-        _ => panic!("Not implemented"),
+        Action::Import(ImportSource::Har(args)) => import_har(args).await,
+        Action::Import(ImportSource::Curl(args)) => import_curl(args),
+        Action::Import(ImportSource::Postman(args)) => import_postman(args).await,
+        Action::Import(ImportSource::Insomnia(args)) => import_insomnia(args).await,
+        Action::Export(args) => export(args).await,
+        Action::Test(args) => {
+            // failed tests exit with 1, for CI
+            let result = test(args).await;
+            if result.is_err() {
+                process_error(result)?;
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Action::Replay(_) => Err(anyhow::anyhow!("replay is only for xdiff")),
+        Action::Proxy(_) => Err(anyhow::anyhow!("proxy is only for xdiff")),
+        Action::Import(ImportSource::Openapi(_)) => {
+            Err(anyhow::anyhow!("openapi import is only for xdiff"))
+        }
+        _ => Err(anyhow::anyhow!("not implemented")),
     };
 
     process_error(result)
@@ -25,7 +47,7 @@ pub async fn main() -> Result<()> {
 async fn parse() -> Result<()> {
     let theme = ColorfulTheme::default();
     let url1: String = Input::with_theme(&theme)
-        .with_prompt("Url")
+        .with_prompt("Url or curl command")
         .interact_text()?;
     let req: rust_xlearn::RequestProfile = url1.parse()?;
 
@@ -50,19 +72,177 @@ async fn parse() -> Result<()> {
     Ok(())
 }
 
-async fn run(args: RunArgs) -> Result<()> {
+async fn run(args: RunArgs<ReqRunArgs>) -> Result<()> {
+    if let Some(filter) = args.tool.filter.as_ref() {
+        Jq::new(filter)?;
+    }
+    let response = ResponseProfile {
+        transform: args.tool.filter.clone(),
+        ..Default::default()
+    };
+    let profile_name = args
+        .profile
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("--profile is required"))?;
+    let config_file = args.config.unwrap_or_else(|| "./xreq.yml".to_string());
+    let config = ReqConfig::load_yaml(&config_file).await?;
+    let extra_args: ExtraArgs = args.extra_params.into();
+    if !extra_args.sides.is_empty() {
+        return Err(anyhow::anyhow!("1: and 2: overrides are only for xdiff"));
+    }
+    let jar = match args.cookie_jar.as_ref() {
+        Some(path) => Some(Arc::new(CookieJar::load(path)?)),
+        None => None,
+    };
+
+    let rows = match args.data.as_ref() {
+        Some(path) => load_data(path)?,
+        None => vec![],
+    };
+
+    let responses = match config.get_scenario(&profile_name) {
+        Some(_) if args.data.is_some() => {
+            return Err(anyhow::anyhow!("--data is not supported for scenarios"))
+        }
+        Some(scenario) => scenario
+            .run(&extra_args, &mut Variables::new(), jar.clone())
+            .await?
+            .into_iter()
+            .map(|(profile, res)| (String::new(), profile, Ok(res)))
+            .collect(),
+        None => {
+            let profile = config.get_profile(&profile_name).ok_or_else(|| {
+                anyhow::anyhow!("no profile {} in config {}", profile_name, config_file)
+            })?;
+            let mut responses = vec![];
+            for (title, profile) in profile.expand(&rows)? {
+                let res = match jar.clone() {
+                    Some(jar) => {
+                        profile.seed_cookies(&jar)?;
+                        profile.send_with(&extra_args, Some(jar)).await
+                    }
+                    None => profile.send(&extra_args).await,
+                };
+                // a failed data row is reported and the others still run
+                responses.push((title, profile, res));
+            }
+            responses
+        }
+    };
+    if let (Some(path), Some(jar)) = (args.cookie_jar, jar) {
+        jar.save(path)?;
+    }
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    let mut failed = 0;
+    for (title, profile, res) in responses {
+        let output = match res {
+            std::result::Result::Ok(res) => {
+                let url = profile.get_url(&extra_args)?;
+                get_response_text(&url, res.into_inner(), &response).await
+            }
+            Err(e) => Err(e),
+        };
+        match (title.is_empty(), output) {
+            (true, output) => writeln!(stdout, "{}", output?)?,
+            (false, std::result::Result::Ok(output)) => {
+                writeln!(stdout, "[{}]\n{}", title, output)?
+            }
+            (false, Err(e)) => {
+                failed += 1;
+                writeln!(stdout, "[{}] error: {:#}\n", title, e)?;
+            }
+        }
+    }
+    if failed > 0 {
+        // failed rows exit with 1 like failed tests
+        process_error(Err(anyhow::anyhow!("{} data rows failed", failed)))?;
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+async fn import_har(args: ImportHarArgs) -> Result<()> {
+    let har: Har = tokio::fs::read_to_string(&args.path).await?.parse()?;
+    let result = serde_yaml::to_string(&har.to_req_config()?)?;
+    write!(std::io::stdout().lock(), "{}", result)?;
+    Ok(())
+}
+
+fn import_curl(args: ImportCurlArgs) -> Result<()> {
+    let req = parse_curl(&args.command)?;
+    let config = ReqConfig::new(vec![(args.profile, req)].into_iter().collect());
+    write!(
+        std::io::stdout().lock(),
+        "{}",
+        serde_yaml::to_string(&config)?
+    )?;
+    Ok(())
+}
+
+async fn import_postman(args: ImportCollectionArgs) -> Result<()> {
+    let collection: PostmanCollection = tokio::fs::read_to_string(&args.path).await?.parse()?;
+    let env = match args.env.as_ref() {
+        Some(path) => Some(tokio::fs::read_to_string(path).await?),
+        None => None,
+    };
+    let (config, warnings) = collection.to_req_config(env.as_deref())?;
+    print_import(&config, &warnings)
+}
+
+async fn import_insomnia(args: ImportCollectionArgs) -> Result<()> {
+    let export: InsomniaExport = tokio::fs::read_to_string(&args.path).await?.parse()?;
+    let (config, warnings) = export.to_req_config(args.env.as_deref())?;
+    print_import(&config, &warnings)
+}
+
+/// The config to stdout, what couldn't be imported to stderr
+fn print_import(config: &ReqConfig, warnings: &[String]) -> Result<()> {
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+    write!(
+        std::io::stdout().lock(),
+        "{}",
+        serde_yaml::to_string(config)?
+    )?;
+    Ok(())
+}
+
+async fn test(args: TestArgs) -> Result<()> {
+    let config_file = args.config.unwrap_or_else(|| "./xreq.yml".to_string());
+    let config = ReqConfig::load_yaml(&config_file).await?;
+    let extra_args: ExtraArgs = args.extra_params.into();
+    if !extra_args.sides.is_empty() {
+        return Err(anyhow::anyhow!("1: and 2: overrides are only for xdiff"));
+    }
+    config.test(&args.profiles, &extra_args).await
+}
+
+async fn export(args: ExportArgs) -> Result<()> {
     let config_file = args.config.unwrap_or_else(|| "./xreq.yml".to_string());
     let config = ReqConfig::load_yaml(&config_file).await?;
     let profile = config
         .get_profile(&args.profile)
         .ok_or_else(|| anyhow::anyhow!("no profile {} in config {}", args.profile, config_file))?;
     let extra_args: ExtraArgs = args.extra_params.into();
-    let url = profile.get_url(&extra_args)?;
-    let res = profile.send(&extra_args).await?.into_inner();
+    if !extra_args.sides.is_empty() {
+        return Err(anyhow::anyhow!("1: and 2: overrides are only for xdiff"));
+    }
+    write!(
+        std::io::stdout().lock(),
+        "{}",
+        profile.export(&extra_args, args.format)?
+    )?;
+    Ok(())
+}
 
+async fn get_response_text(url: &str, res: Response, response: &ResponseProfile) -> Result<String> {
     let status = get_status_text(&res)?;
     let headers = get_header_text(&res, &[])?;
-    let body = get_body_text(res, &[]).await?;
+    let body = get_filtered_body_text(res, response).await?;
 
     let mut output = String::new();
 
@@ -91,9 +271,5 @@ async fn run(args: RunArgs) -> Result<()> {
         write!(&mut output, "{}", &body)?;
     }
 
-    let stdout = std::io::stdout();
-    let mut stdout = stdout.lock();
-    writeln!(stdout, "{}", output)?;
-
-    Ok(())
+    Ok(output)
 }
@@ -1,57 +1,288 @@
 use anyhow::*;
 
-use crate::ExtraArgs;
+use crate::{ArgValue, ExportFormat, ExtraArgs, FilePart, LogFormat, Side};
 use clap::{Parser, Subcommand};
+use reqwest::Method;
+use std::net::SocketAddr;
+use url::Url;
 
 #[derive(Parser, Debug, Clone)]
 #[clap(version,author,about,long_about=None)]
-pub struct Args {
+pub struct Args<T: clap::Args> {
     #[clap(subcommand)]
-    pub action: Action,
+    pub action: Action<T>,
 }
 
 #[derive(Subcommand, Debug, Clone)]
 #[non_exhaustive]
-pub enum Action {
+pub enum Action<T: clap::Args> {
     /// Diff two responses based on profile
-    Run(RunArgs),
+    Run(RunArgs<T>),
     Parse,
+    /// Replay requests of an access log against two targets
+    Replay(ReplayArgs),
+    /// Serve the primary upstream and diff its responses against a mirrored candidate
+    Proxy(ProxyArgs),
+    /// Generate a config from recorded requests
+    #[clap(subcommand)]
+    Import(ImportSource),
+    /// Print the request of a profile as a curl/httpie command or an http file
+    Export(ExportArgs),
+    /// Send profiles and check the `expect` of each, exits with 1 if any failed
+    Test(TestArgs),
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ImportSource {
+    /// HAR file exported by browser devtools
+    Har(ImportHarArgs),
+    /// curl command, as copied from browser devtools
+    Curl(ImportCurlArgs),
+    /// OpenAPI 3 spec, one diff profile per operation
+    Openapi(ImportOpenapiArgs),
+    /// Postman v2.1 collection
+    Postman(ImportCollectionArgs),
+    /// Insomnia v4 export
+    Insomnia(ImportCollectionArgs),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ImportCollectionArgs {
+    /// collection or export path
+    #[clap(value_parser)]
+    pub path: String,
+
+    /// postman: an exported environment file, insomnia: a sub environment name
+    #[clap(long, value_parser)]
+    pub env: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ImportHarArgs {
+    /// HAR file path
+    #[clap(value_parser)]
+    pub path: String,
+
+    /// generate a ReqConfig for xreq instead of a DiffConfig
+    #[clap(long)]
+    pub xreq: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ImportOpenapiArgs {
+    /// spec path, yaml or json
+    #[clap(value_parser)]
+    pub path: String,
+
+    #[clap(long, value_parser)]
+    pub base1: Url,
+
+    #[clap(long, value_parser)]
+    pub base2: Url,
+
+    /// methods of the operations to import, only GET by default for safety
+    #[clap(long = "method", value_parser, default_value = "GET")]
+    pub methods: Vec<Method>,
 }
 
 #[derive(Parser, Debug, Clone)]
-pub struct RunArgs {
+pub struct ImportCurlArgs {
+    /// the whole curl command, quoted
+    #[clap(value_parser)]
+    pub command: String,
+
     /// profile name
-    #[clap(short, long, value_parser)]
+    #[clap(short, long, value_parser, default_value = "curl")]
     pub profile: String,
 
+    /// generate a ReqConfig for xreq instead of a DiffConfig
+    #[clap(long)]
+    pub xreq: bool,
+}
+
+/// `run` flags of both tools, `T` has the flags of one tool
+#[derive(Parser, Debug, Clone)]
+pub struct RunArgs<T: clap::Args> {
+    /// profile name, required unless xdiff runs a HAR file
+    #[clap(short, long, value_parser)]
+    pub profile: Option<String>,
+
     /// Override args
     /// For query, like '-e key=value'
+    /// For path param, like '-e :key=value'
     /// For header, like '-e %key=value'
     /// For body, like '-e @key=value'
+    /// For multipart file, like '-e +key=./file.png;type=image/png;filename=me.png'
+    /// Body keys can be nested, like '-e @user.tags[0]=x', query keys are literal, like '-e page.size=10'
+    /// Query and body values take json with ':=', like '-e @filter:={"a":1}',
+    /// a file with '=@', like '-e @note=@./note.txt' or '-e @user:=@./user.json',
+    /// or remove a configured value with '!', like '-e @!user.age'
+    /// For only one side of a diff, prefix with '1:' or '2:', like '-e 2:%x-api-version=2'
     #[clap(short, long, value_parser=parse_key_val,number_of_values=1)]
     pub extra_params: Vec<KeyVal>,
 
     /// config file path
     #[clap(short, long, value_parser)]
     pub config: Option<String>,
+
+    /// cookie jar file, loaded before the run and saved after it
+    #[clap(long, value_parser)]
+    pub cookie_jar: Option<String>,
+
+    /// csv or jsonl file, each row supplies `{{name}}` variables for one run
+    #[clap(long, value_parser)]
+    pub data: Option<String>,
+
+    #[clap(flatten)]
+    pub tool: T,
+}
+
+/// `run` flags of xdiff
+#[derive(clap::Args, Debug, Clone)]
+pub struct DiffRunArgs {
+    /// HAR file, each recorded response is diffed with a live one,
+    /// the response rules of `--profile` are used if given
+    #[clap(long, value_parser)]
+    pub har: Option<String>,
+
+    /// methods of the recorded requests resent with --har, only GET by default for safety
+    #[clap(long = "method", value_parser, default_value = "GET")]
+    pub methods: Vec<Method>,
+}
+
+/// `run` flags of xreq
+#[derive(clap::Args, Debug, Clone)]
+pub struct ReqRunArgs {
+    /// jq filter applied to a json response body, like '.items | map({id, name})'
+    #[clap(long, value_parser)]
+    pub filter: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct TestArgs {
+    /// profiles to test, all profiles if none
+    #[clap(short, long = "profile", value_parser)]
+    pub profiles: Vec<String>,
+
+    /// Override args, like `run`
+    #[clap(short, long, value_parser=parse_key_val,number_of_values=1)]
+    pub extra_params: Vec<KeyVal>,
+
+    /// config file path
+    #[clap(short, long, value_parser)]
+    pub config: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ExportArgs {
+    /// profile name
+    #[clap(short, long, value_parser)]
+    pub profile: String,
+
+    /// Override args, like `run`
+    #[clap(short, long, value_parser=parse_key_val,number_of_values=1)]
+    pub extra_params: Vec<KeyVal>,
+
+    /// config file path
+    #[clap(short, long, value_parser)]
+    pub config: Option<String>,
+
+    /// output format
+    #[clap(long = "as", value_enum, default_value = "curl")]
+    pub format: ExportFormat,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ReplayArgs {
+    /// access log path
+    #[clap(long, value_parser)]
+    pub log: String,
+
+    #[clap(long, value_enum, default_value = "combined")]
+    pub format: LogFormat,
+
+    #[clap(long, value_parser)]
+    pub target1: Url,
+
+    #[clap(long, value_parser)]
+    pub target2: Url,
+
+    /// methods to replay, only GET by default for safety
+    #[clap(long = "method", value_parser, default_value = "GET")]
+    pub methods: Vec<Method>,
+
+    /// fraction of the requests to replay, like 0.1
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    pub sample: f64,
+
+    /// max requests per second
+    #[clap(long, value_parser)]
+    pub rate: Option<f64>,
+
+    /// headers left out of the diff, like 'date,etag'
+    #[clap(long, value_parser, value_delimiter = ',')]
+    pub skip_headers: Vec<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ProxyArgs {
+    /// address to listen on
+    #[clap(long, value_parser, default_value = "127.0.0.1:8080")]
+    pub listen: SocketAddr,
+
+    /// upstream whose responses are returned to the client
+    #[clap(long, value_parser)]
+    pub primary: Url,
+
+    /// upstream the requests are mirrored to
+    #[clap(long, value_parser)]
+    pub candidate: Url,
+
+    /// config file path, the response rules of `--profile` are used for the diff
+    #[clap(short, long, value_parser)]
+    pub config: Option<String>,
+
+    /// profile name
+    #[clap(short, long, value_parser)]
+    pub profile: Option<String>,
+
+    /// headers left out of the diff, like 'date,etag'
+    #[clap(long, value_parser, value_delimiter = ',')]
+    pub skip_headers: Vec<String>,
+
+    /// file the differences are appended to, stdout if not set
+    #[clap(long, value_parser)]
+    pub report: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct KeyVal {
     key: String,
-    value: String,
+    value: ArgValue,
     key_type: KeyValType,
+    side: Option<Side>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum KeyValType {
+    Path,
     Query,
     Header,
     Body,
+    File,
 }
 
 pub fn parse_key_val(s: &str) -> Result<KeyVal> {
-    let mut parts = s.splitn(2, '=');
+    let s = s.trim();
+    let (side, arg) = match (s.strip_prefix("1:"), s.strip_prefix("2:")) {
+        (Some(arg), _) => (Some(Side::Req1), arg),
+        (_, Some(arg)) => (Some(Side::Req2), arg),
+        _ => (None, s),
+    };
+    let (key, value) = match arg.split_once('=') {
+        Some((key, value)) => (key.trim(), Some(value.trim())),
+        None => (arg.trim(), None),
+    };
 
     // let retrieve = |v: Option<&str>, str: String| -> Result<&str> {
     //     Ok(v.ok_or_else(|| anyhow!("Invalid key value:{}", str))?
@@ -60,55 +291,185 @@ pub fn parse_key_val(s: &str) -> Result<KeyVal> {
     // let key = retrieve(parts.next(), s.to_string())?;
     // let value = retrieve(parts.next(), s.to_string())?;
 
-    let key = parts
-        .next()
-        .ok_or_else(|| anyhow!("Invalid key :{}", s))?
-        .trim();
-    let value = parts
-        .next()
-        .ok_or_else(|| anyhow!("Invalid value :{}", s))?
-        .trim();
-
     let (key_type, key) = match key.chars().next() {
+        Some(':') => (KeyValType::Path, &key[1..]),
         Some('%') => (KeyValType::Header, &key[1..]),
         Some('@') => (KeyValType::Body, &key[1..]),
-        Some(v) if v.is_ascii_alphabetic() => (KeyValType::Query, key),
+        Some('+') => (KeyValType::File, &key[1..]),
+        Some(v) if v.is_ascii_alphabetic() || v == '!' => (KeyValType::Query, key),
         _ => return Err(anyhow!("Invalid key value type:{}", key)),
     };
 
+    let (key, value) = match (key.strip_prefix('!'), key.strip_suffix(':'), value) {
+        (Some(key), _, None) => (key, ArgValue::Delete),
+        (None, Some(key), Some(value)) => {
+            let json = match value.strip_prefix('@') {
+                Some(file) => read_file(file)?,
+                None => value.to_string(),
+            };
+            let json = serde_json::from_str(&json)
+                .map_err(|e| anyhow!("Invalid json value:{}, {}", value, e))?;
+            (key, ArgValue::Json(json))
+        }
+        (None, None, Some(value)) => match value.strip_prefix('@') {
+            Some(file) if matches!(key_type, KeyValType::Query | KeyValType::Body) => {
+                (key, ArgValue::Json(read_file(file)?.into()))
+            }
+            _ => (key, ArgValue::Text(value.to_string())),
+        },
+        _ => return Err(anyhow!("Invalid key value:{}", s)),
+    };
+
+    match (&key_type, &value) {
+        (KeyValType::Query | KeyValType::Body, _) => {}
+        (KeyValType::File, ArgValue::Text(v)) => {
+            v.parse::<FilePart>()?;
+        }
+        (_, ArgValue::Text(_)) => {}
+        _ => return Err(anyhow!("Only query and body support ':=' and '!':{}", s)),
+    }
+    if key.is_empty() {
+        return Err(anyhow!("Invalid key :{}", s));
+    }
+
     Ok(KeyVal {
         key: key.to_string(),
-        value: value.to_string(),
+        value,
         key_type: key_type,
+        side,
     })
 }
 
+fn read_file(path: &str) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|e| anyhow!("read file {} error: {}", path, e))
+}
+
+/// Path, header and file values are always text, see `parse_key_val`
+fn into_text(value: ArgValue) -> String {
+    match value {
+        ArgValue::Text(v) => v,
+        v => v.to_json().map(|v| v.to_string()).unwrap_or_default(),
+    }
+}
+
 impl From<Vec<KeyVal>> for ExtraArgs {
     fn from(args: Vec<KeyVal>) -> Self {
-        let mut headers = vec![];
-        let mut querys = vec![];
-        let mut bodys = vec![];
-
-        for arg in args {
-            match arg.key_type {
-                KeyValType::Query => querys.push((arg.key, arg.value)),
-                KeyValType::Header => headers.push((arg.key, arg.value)),
-                KeyValType::Body => bodys.push((arg.key, arg.value)),
+        let (shared, sided): (Vec<_>, Vec<_>) = args.into_iter().partition(|v| v.side.is_none());
+        let mut extra = collect_args(shared);
+        for side in [Side::Req1, Side::Req2] {
+            let args: Vec<_> = sided
+                .iter()
+                .filter(|v| v.side == Some(side))
+                .cloned()
+                .collect();
+            if !args.is_empty() {
+                extra.sides.insert(side, collect_args(args));
             }
         }
-        Self {
-            headers: headers,
-            bodys: bodys,
-            querys: querys,
+        extra
+    }
+}
+
+fn collect_args(args: Vec<KeyVal>) -> ExtraArgs {
+    let mut paths = vec![];
+    let mut headers = vec![];
+    let mut querys = vec![];
+    let mut bodys = vec![];
+    let mut files = vec![];
+
+    for arg in args {
+        match arg.key_type {
+            KeyValType::Path => paths.push((arg.key, into_text(arg.value))),
+            KeyValType::Query => querys.push((arg.key, arg.value)),
+            KeyValType::Header => headers.push((arg.key, into_text(arg.value))),
+            KeyValType::Body => bodys.push((arg.key, arg.value)),
+            KeyValType::File => files.push((arg.key, into_text(arg.value))),
         }
     }
+    ExtraArgs {
+        paths,
+        headers,
+        bodys,
+        querys,
+        files,
+        sides: Default::default(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use serde_json::json;
 
     #[test]
     fn t1() {
         trycmd::TestCases::new().case("fixtures/cmd_test.md");
     }
+
+    #[test]
+    fn t2() {
+        let arg = parse_key_val("@user.address.city=Paris").unwrap();
+        assert_eq!(arg.key, "user.address.city");
+        assert_eq!(arg.value.to_json(), Some(json!("Paris")));
+        let arg = parse_key_val("@id=1").unwrap();
+        assert_eq!(arg.value.to_json(), Some(json!(1)));
+        let arg = parse_key_val(r#"@filter:={"a":1}"#).unwrap();
+        assert_eq!(arg.key, "filter");
+        assert_eq!(arg.value, ArgValue::Json(json!({"a": 1})));
+        let arg = parse_key_val("!debug").unwrap();
+        assert_eq!(
+            (arg.key_type, arg.value),
+            (KeyValType::Query, ArgValue::Delete)
+        );
+        let arg = parse_key_val("@note=@fixtures/diff_test_txt1.txt").unwrap();
+        let content = std::fs::read_to_string("fixtures/diff_test_txt1.txt").unwrap();
+        assert_eq!(arg.value, ArgValue::Json(json!(content)));
+        assert!(parse_key_val("@filter:={").is_err());
+        assert!(parse_key_val("%!x-api-key").is_err());
+        assert!(parse_key_val("@user").is_err());
+        assert!(parse_key_val("3:%x-api-version=3").is_err());
+
+        let mut body = json!({"user": {"age": 3, "name": "tom"}});
+        let args: ExtraArgs = vec![
+            parse_key_val("@!user.age").unwrap(),
+            parse_key_val("@tags[0]=x").unwrap(),
+        ]
+        .into();
+        for (k, v) in &args.bodys {
+            v.apply(k, &mut body).unwrap();
+        }
+        assert_eq!(body, json!({"user": {"name": "tom"}, "tags": ["x"]}));
+    }
+
+    #[test]
+    fn t3() {
+        let args: ExtraArgs = vec![
+            parse_key_val("%x-api-version=1").unwrap(),
+            parse_key_val("2:%x-api-version=2").unwrap(),
+            parse_key_val("2:debug=1").unwrap(),
+            parse_key_val("1:@!title").unwrap(),
+            parse_key_val("2:!q").unwrap(),
+        ]
+        .into();
+        let req1 = args.for_side(Side::Req1);
+        assert_eq!(req1.headers, vec![("x-api-version".into(), "1".into())]);
+        assert!(req1.querys.is_empty() && req1.sides.is_empty());
+        assert_eq!(req1.bodys, vec![("title".into(), ArgValue::Delete)]);
+        let req2 = args.for_side(Side::Req2);
+        assert_eq!(
+            req2.headers,
+            vec![
+                ("x-api-version".into(), "1".into()),
+                ("x-api-version".into(), "2".into())
+            ]
+        );
+        assert_eq!(
+            req2.querys,
+            vec![
+                ("debug".into(), ArgValue::Text("1".into())),
+                ("q".into(), ArgValue::Delete)
+            ]
+        );
+        assert!(req2.bodys.is_empty());
+    }
 }
@@ -0,0 +1,892 @@
+use crate::{
+    template::{render_partial, value_to_string},
+    FilePart, GraphqlRequest, ReqConfig, RequestProfile, Variables,
+};
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use regex::Regex;
+use reqwest::header::{self, HeaderName, HeaderValue};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+/// A Postman v2.1 collection, only the fields used here
+#[derive(Debug, Deserialize, Clone)]
+pub struct PostmanCollection {
+    #[serde(default)]
+    item: Vec<PostmanItem>,
+    #[serde(default)]
+    variable: Vec<PostmanKv>,
+    #[serde(default)]
+    event: Vec<PostmanEvent>,
+    auth: Option<PostmanAuth>,
+}
+
+/// A request, or a folder of items
+#[derive(Debug, Deserialize, Clone)]
+struct PostmanItem {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    item: Vec<PostmanItem>,
+    request: Option<PostmanRequest>,
+    #[serde(default)]
+    event: Vec<PostmanEvent>,
+    auth: Option<PostmanAuth>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+enum PostmanRequest {
+    Url(String),
+    Full(Box<PostmanHttpRequest>),
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct PostmanHttpRequest {
+    method: Option<String>,
+    url: Option<PostmanUrl>,
+    #[serde(default)]
+    header: Vec<PostmanKv>,
+    body: Option<PostmanBody>,
+    auth: Option<PostmanAuth>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+enum PostmanUrl {
+    Raw(String),
+    Parts {
+        #[serde(default)]
+        raw: String,
+        /// values of `:name` path segments
+        #[serde(default)]
+        variable: Vec<PostmanKv>,
+    },
+}
+
+/// A header, variable, form field or auth attribute
+#[derive(Debug, Deserialize, Clone)]
+struct PostmanKv {
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    disabled: bool,
+    /// environment values use `enabled` instead of `disabled`
+    enabled: Option<bool>,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    src: Option<Value>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct PostmanBody {
+    #[serde(default)]
+    mode: String,
+    raw: Option<String>,
+    #[serde(default)]
+    urlencoded: Vec<PostmanKv>,
+    #[serde(default)]
+    formdata: Vec<PostmanKv>,
+    graphql: Option<PostmanGraphql>,
+    file: Option<PostmanKv>,
+    options: Option<Value>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct PostmanGraphql {
+    #[serde(default)]
+    query: String,
+    #[serde(default)]
+    variables: String,
+}
+
+/// `{"type": "basic", "basic": [{"key": "username", "value": "me"}]}`
+#[derive(Debug, Deserialize, Clone)]
+struct PostmanAuth {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(flatten)]
+    attrs: HashMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct PostmanEvent {
+    listen: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct PostmanEnvironment {
+    #[serde(default)]
+    values: Vec<PostmanKv>,
+}
+
+/// An Insomnia v4 export, its resources are linked by `parentId`
+#[derive(Debug, Deserialize, Clone)]
+pub struct InsomniaExport {
+    #[serde(default)]
+    resources: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct InsomniaRequest {
+    #[serde(default)]
+    parent_id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    method: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    body: InsomniaBody,
+    #[serde(default)]
+    headers: Vec<InsomniaKv>,
+    #[serde(default)]
+    parameters: Vec<InsomniaKv>,
+    #[serde(default)]
+    authentication: Value,
+    #[serde(default)]
+    pre_request_script: String,
+    #[serde(default)]
+    after_response_script: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct InsomniaBody {
+    mime_type: Option<String>,
+    text: Option<String>,
+    #[serde(default)]
+    params: Vec<InsomniaKv>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct InsomniaKv {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    value: String,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    file_name: Option<String>,
+}
+
+/// A request of a collection, before its variables are substituted
+#[derive(Debug, Default)]
+struct Draft {
+    method: String,
+    url: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: DraftBody,
+}
+
+#[derive(Debug, Default)]
+enum DraftBody {
+    #[default]
+    None,
+    Text {
+        content_type: Option<String>,
+        text: String,
+    },
+    Form(Vec<(String, String)>),
+    Multipart {
+        fields: Vec<(String, String)>,
+        files: Vec<(String, String)>,
+    },
+    Graphql {
+        query: String,
+        variables: String,
+    },
+    File(String),
+}
+
+/// Collects the profiles of a collection and what couldn't be imported
+#[derive(Debug, Default)]
+struct Importer {
+    vars: Variables,
+    profiles: HashMap<String, RequestProfile>,
+    names: HashMap<String, usize>,
+    missing: BTreeSet<String>,
+    warnings: Vec<String>,
+}
+
+impl FromStr for PostmanCollection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+impl FromStr for InsomniaExport {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+impl PostmanCollection {
+    /// Profiles named after their folders and request names, with the collection variables
+    /// and the values of an exported environment substituted, and the import warnings
+    pub fn to_req_config(&self, environment: Option<&str>) -> Result<(ReqConfig, Vec<String>)> {
+        let mut imp = Importer::default();
+        for var in &self.variable {
+            imp.vars.insert(var.key.clone(), var.value.clone());
+        }
+        if let Some(env) = environment {
+            let env: PostmanEnvironment = serde_json::from_str(env)?;
+            for var in env.values.into_iter().filter(|v| v.enabled != Some(false)) {
+                imp.vars.insert(var.key, var.value);
+            }
+        }
+        report_events(&mut imp, "collection", &self.event);
+        walk_postman(&mut imp, &self.item, &[], self.auth.as_ref())?;
+        Ok(imp.finish())
+    }
+}
+
+fn walk_postman(
+    imp: &mut Importer,
+    items: &[PostmanItem],
+    folders: &[String],
+    auth: Option<&PostmanAuth>,
+) -> Result<()> {
+    for item in items {
+        let mut path = folders.to_vec();
+        path.push(item.name.clone());
+        let title = path.join("/");
+        report_events(imp, &title, &item.event);
+        let auth = item.auth.as_ref().or(auth);
+        let (method, url, header, body, request_auth) = match item.request.as_ref() {
+            None => {
+                walk_postman(imp, &item.item, &path, auth)?;
+                continue;
+            }
+            Some(PostmanRequest::Url(url)) => (
+                None,
+                Some(postman_url(imp, &title, url, &[])),
+                &[][..],
+                None,
+                None,
+            ),
+            Some(PostmanRequest::Full(req)) => {
+                let url = req.url.as_ref().map(|url| match url {
+                    PostmanUrl::Raw(raw) => postman_url(imp, &title, raw, &[]),
+                    PostmanUrl::Parts { raw, variable } => postman_url(imp, &title, raw, variable),
+                });
+                (
+                    req.method.clone(),
+                    url,
+                    &req.header[..],
+                    req.body.as_ref(),
+                    req.auth.as_ref(),
+                )
+            }
+        };
+        let mut draft = Draft {
+            method: method.unwrap_or_else(|| "GET".to_string()),
+            url: url.unwrap_or_default(),
+            headers: header
+                .iter()
+                .filter(|h| !h.disabled)
+                .map(|h| (h.key.clone(), value_to_string(&h.value)))
+                .collect(),
+            ..Default::default()
+        };
+        if let Some(auth) = request_auth.or(auth) {
+            postman_auth(imp, &title, auth, &mut draft);
+        }
+        if let Some(body) = body {
+            draft.body = postman_body(imp, &title, body);
+        }
+        imp.add(&path, draft);
+    }
+    Ok(())
+}
+
+/// The url with its `:name` path segments substituted, the unresolved ones are reported
+fn postman_url(imp: &mut Importer, title: &str, raw: &str, vars: &[PostmanKv]) -> String {
+    let (url, rest) = raw.split_at(raw.find(['?', '#']).unwrap_or(raw.len()));
+    let start = url.find("://").map(|i| i + 3).unwrap_or_default();
+    let path = url[start..].find('/').map_or(url.len(), |i| start + i);
+    let mut output = url[..path].to_string();
+    for seg in url[path..].split('/').skip(1) {
+        output.push('/');
+        let name = match seg.strip_prefix(':') {
+            Some(name) if !name.is_empty() => name,
+            _ => {
+                output.push_str(seg);
+                continue;
+            }
+        };
+        match vars
+            .iter()
+            .find(|v| v.key == name)
+            .map(|v| value_to_string(&v.value))
+        {
+            Some(value) if !value.is_empty() => output.push_str(&value),
+            _ => {
+                imp.warnings
+                    .push(format!("{}: path variable :{} is kept as is", title, name));
+                output.push_str(seg);
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn postman_auth(imp: &mut Importer, title: &str, auth: &PostmanAuth, draft: &mut Draft) {
+    let attr = |key: &str| {
+        auth.attrs
+            .get(&auth.kind)
+            .and_then(|v| v.as_array())
+            .and_then(|v| v.iter().find(|a| a["key"] == key))
+            .map(|a| value_to_string(&a["value"]))
+            .unwrap_or_default()
+    };
+    match auth.kind.as_str() {
+        "noauth" => {}
+        "basic" => {
+            let user = format!("{}:{}", attr("username"), attr("password"));
+            draft.headers.push(basic_auth(imp, &user));
+        }
+        "bearer" => draft.headers.push((
+            "authorization".to_string(),
+            format!("Bearer {}", attr("token")),
+        )),
+        "apikey" => match attr("in").as_str() {
+            "query" => draft.query.push((attr("key"), attr("value"))),
+            _ => draft.headers.push((attr("key"), attr("value"))),
+        },
+        kind => imp
+            .warnings
+            .push(format!("{}: {} auth is not supported", title, kind)),
+    }
+}
+
+fn postman_body(imp: &mut Importer, title: &str, body: &PostmanBody) -> DraftBody {
+    let fields = |kvs: &[PostmanKv]| -> Vec<(String, String)> {
+        kvs.iter()
+            .filter(|v| !v.disabled && v.kind.as_deref() != Some("file"))
+            .map(|v| (v.key.clone(), value_to_string(&v.value)))
+            .collect()
+    };
+    match body.mode.as_str() {
+        "raw" => {
+            let language = body
+                .options
+                .as_ref()
+                .and_then(|v| v.pointer("/raw/language"))
+                .and_then(|v| v.as_str());
+            let content_type = match language {
+                Some("json") => "application/json",
+                Some("xml") => "application/xml",
+                Some("html") => "text/html",
+                Some("javascript") => "application/javascript",
+                _ => "text/plain",
+            };
+            DraftBody::Text {
+                content_type: Some(content_type.to_string()),
+                text: body.raw.clone().unwrap_or_default(),
+            }
+        }
+        "urlencoded" => DraftBody::Form(fields(&body.urlencoded)),
+        "formdata" => DraftBody::Multipart {
+            fields: fields(&body.formdata),
+            files: body
+                .formdata
+                .iter()
+                .filter(|v| !v.disabled && v.kind.as_deref() == Some("file"))
+                .filter_map(|v| match v.src.as_ref() {
+                    Some(Value::String(src)) => Some((v.key.clone(), src.clone())),
+                    _ => None,
+                })
+                .collect(),
+        },
+        "graphql" => {
+            let graphql = body.graphql.clone();
+            DraftBody::Graphql {
+                query: graphql
+                    .as_ref()
+                    .map(|g| g.query.clone())
+                    .unwrap_or_default(),
+                variables: graphql.map(|g| g.variables).unwrap_or_default(),
+            }
+        }
+        "file" => match body.file.as_ref().and_then(|f| f.src.as_ref()) {
+            Some(Value::String(src)) => DraftBody::File(src.clone()),
+            _ => DraftBody::None,
+        },
+        mode => {
+            imp.warnings
+                .push(format!("{}: {} body is not supported", title, mode));
+            DraftBody::None
+        }
+    }
+}
+
+/// Scripts can't run here, they are reported
+fn report_events(imp: &mut Importer, title: &str, events: &[PostmanEvent]) {
+    for event in events {
+        imp.warnings.push(format!(
+            "{}: {} script is not supported",
+            title, event.listen
+        ));
+    }
+}
+
+impl InsomniaExport {
+    /// Profiles named after their folders and request names, with the base environment,
+    /// the named sub environment and folder environments substituted, and the import warnings
+    pub fn to_req_config(&self, environment: Option<&str>) -> Result<(ReqConfig, Vec<String>)> {
+        let mut imp = Importer::default();
+        let mut base = Variables::new();
+        let mut groups: HashMap<&str, (&str, &str, &Value)> = HashMap::new();
+        fn text<'a>(v: &'a Value, k: &str) -> &'a str {
+            v[k].as_str().unwrap_or_default()
+        }
+        let envs: Vec<_> = self
+            .resources
+            .iter()
+            .filter(|r| text(r, "_type") == "environment")
+            .collect();
+        let base_env = envs
+            .iter()
+            .find(|e| text(e, "parentId").starts_with("wrk_"));
+        if let Some(env) = base_env {
+            flatten(&env["data"], "", &mut base);
+        }
+        if let Some(name) = environment {
+            let env = envs
+                .iter()
+                .find(|e| {
+                    text(e, "name") == name
+                        && base_env.is_some_and(|b| text(e, "parentId") == text(b, "_id"))
+                })
+                .ok_or_else(|| anyhow!("no environment {} in the export", name))?;
+            flatten(&env["data"], "", &mut base);
+        }
+        for r in &self.resources {
+            if text(r, "_type") == "request_group" {
+                groups.insert(
+                    text(r, "_id"),
+                    (text(r, "parentId"), text(r, "name"), &r["environment"]),
+                );
+            }
+        }
+
+        for r in &self.resources {
+            match text(r, "_type") {
+                "request" => {}
+                kind @ ("grpc_request" | "websocket_request" | "unit_test_suite" | "unit_test") => {
+                    imp.warnings
+                        .push(format!("{}: {} is not supported", text(r, "name"), kind));
+                    continue;
+                }
+                _ => continue,
+            }
+            let req: InsomniaRequest = serde_json::from_value(r.clone())?;
+
+            // folders from the outermost, their environments override the outer ones
+            let mut folders = vec![];
+            let mut parent = req.parent_id.as_str();
+            while let Some((next, name, env)) = groups.get(parent) {
+                folders.insert(0, (name.to_string(), *env));
+                parent = next;
+            }
+            imp.vars = base.clone();
+            for (_, env) in &folders {
+                flatten(env, "", &mut imp.vars);
+            }
+            let mut path: Vec<_> = folders.into_iter().map(|(name, _)| name).collect();
+            path.push(req.name.clone());
+            let title = path.join("/");
+
+            for (kind, script) in [
+                ("pre-request", &req.pre_request_script),
+                ("after-response", &req.after_response_script),
+            ] {
+                if !script.trim().is_empty() {
+                    imp.warnings
+                        .push(format!("{}: {} script is not supported", title, kind));
+                }
+            }
+            let mut draft = Draft {
+                method: req.method.clone(),
+                url: req.url.clone(),
+                query: enabled(&req.parameters),
+                headers: enabled(&req.headers),
+                body: insomnia_body(&req.body),
+            };
+            insomnia_auth(&mut imp, &title, &req.authentication, &mut draft);
+            imp.add(&path, draft);
+        }
+        Ok(imp.finish())
+    }
+}
+
+fn enabled(kvs: &[InsomniaKv]) -> Vec<(String, String)> {
+    kvs.iter()
+        .filter(|v| !v.disabled && v.kind.as_deref() != Some("file"))
+        .map(|v| (v.name.clone(), v.value.clone()))
+        .collect()
+}
+
+fn insomnia_body(body: &InsomniaBody) -> DraftBody {
+    let mime = match body.mime_type.as_deref() {
+        Some(mime) if !mime.is_empty() => mime,
+        _ => return DraftBody::None,
+    };
+    match mime {
+        "application/x-www-form-urlencoded" => DraftBody::Form(enabled(&body.params)),
+        "multipart/form-data" => DraftBody::Multipart {
+            fields: enabled(&body.params),
+            files: body
+                .params
+                .iter()
+                .filter(|v| !v.disabled && v.kind.as_deref() == Some("file"))
+                .filter_map(|v| v.file_name.clone().map(|f| (v.name.clone(), f)))
+                .collect(),
+        },
+        "application/graphql" => {
+            let text = body.text.clone().unwrap_or_default();
+            let value: Value = serde_json::from_str(&text).unwrap_or_default();
+            DraftBody::Graphql {
+                query: value["query"].as_str().unwrap_or_default().to_string(),
+                variables: match &value["variables"] {
+                    Value::Null => String::new(),
+                    v => v.to_string(),
+                },
+            }
+        }
+        mime => DraftBody::Text {
+            content_type: Some(mime.to_string()),
+            text: body.text.clone().unwrap_or_default(),
+        },
+    }
+}
+
+fn insomnia_auth(imp: &mut Importer, title: &str, auth: &Value, draft: &mut Draft) {
+    let text = |k: &str| auth[k].as_str().unwrap_or_default().to_string();
+    if auth["disabled"] == true {
+        return;
+    }
+    match auth["type"].as_str() {
+        None | Some("none") => {}
+        Some("basic") => {
+            let user = format!("{}:{}", text("username"), text("password"));
+            draft.headers.push(basic_auth(imp, &user));
+        }
+        Some("bearer") => {
+            let prefix = match text("prefix") {
+                p if p.is_empty() => "Bearer".to_string(),
+                p => p,
+            };
+            draft.headers.push((
+                "authorization".to_string(),
+                format!("{} {}", prefix, text("token")),
+            ));
+        }
+        Some("apikey") => match text("addTo").as_str() {
+            "queryParams" => draft.query.push((text("key"), text("value"))),
+            _ => draft.headers.push((text("key"), text("value"))),
+        },
+        Some(kind) => imp
+            .warnings
+            .push(format!("{}: {} auth is not supported", title, kind)),
+    }
+}
+
+/// Nested environment values as dotted names, like `api.host`
+fn flatten(value: &Value, prefix: &str, vars: &mut Variables) {
+    if let Value::Object(obj) = value {
+        for (k, v) in obj {
+            let name = match prefix.is_empty() {
+                true => k.clone(),
+                false => format!("{}.{}", prefix, k),
+            };
+            match v {
+                Value::Object(_) => flatten(v, &name, vars),
+                v => {
+                    vars.insert(name, v.clone());
+                }
+            }
+        }
+    }
+}
+
+/// The credentials are substituted before they're encoded
+fn basic_auth(imp: &mut Importer, user: &str) -> (String, String) {
+    let user = imp.render(user);
+    let token = base64::engine::general_purpose::STANDARD.encode(user);
+    ("authorization".to_string(), format!("Basic {}", token))
+}
+
+impl Importer {
+    /// Substitute the known variables, insomnia's `{{ _.name }}` is the same as `{{name}}`
+    fn render(&mut self, s: &str) -> String {
+        let s = s.replace("{{ _.", "{{").replace("{{_.", "{{");
+        let (output, missing) = render_partial(&s, &self.vars);
+        self.missing.extend(missing);
+        output
+    }
+
+    fn add(&mut self, path: &[String], draft: Draft) {
+        let title = path.join("/");
+        match self.profile(draft) {
+            Ok(profile) => {
+                let name = match slug(&path.join(" ")) {
+                    name if name.is_empty() => format!("request_{}", self.profiles.len() + 1),
+                    name => name,
+                };
+                let count = self.names.entry(name.clone()).or_insert(0);
+                *count += 1;
+                let name = match *count {
+                    1 => name,
+                    n => format!("{}_{}", name, n),
+                };
+                self.profiles.insert(name, profile);
+            }
+            Err(e) => self.warnings.push(format!("{}: skipped, {:#}", title, e)),
+        }
+    }
+
+    fn profile(&mut self, draft: Draft) -> Result<RequestProfile> {
+        let mut url = self.render(draft.url.trim());
+        // postman and insomnia default to http without a scheme
+        if !url.contains("://") {
+            url = format!("http://{}", url);
+        }
+        let mut profile: RequestProfile = url
+            .parse()
+            .map_err(|e| anyhow!("invalid url {}: {}", url, e))?;
+        profile.method = self.render(&draft.method).to_uppercase().parse()?;
+        let mut query = match profile.params.take() {
+            Some(Value::Object(query)) => query,
+            _ => serde_json::Map::new(),
+        };
+        for (k, v) in &draft.query {
+            query.insert(self.render(k), Value::String(self.render(v)));
+        }
+        if !query.is_empty() {
+            profile.params = Some(Value::Object(query));
+        }
+        for (k, v) in &draft.headers {
+            profile.headers.append(
+                HeaderName::from_str(&self.render(k))?,
+                HeaderValue::from_str(&self.render(v))?,
+            );
+        }
+
+        let content_type = |ct: &str| -> Result<HeaderValue> { Ok(ct.parse()?) };
+        match draft.body {
+            DraftBody::None => {}
+            DraftBody::Text {
+                content_type: ct,
+                text,
+            } => {
+                if let Some(ct) = ct {
+                    if !profile.headers.contains_key(header::CONTENT_TYPE) {
+                        profile
+                            .headers
+                            .insert(header::CONTENT_TYPE, content_type(&ct)?);
+                    }
+                }
+                let ct = profile
+                    .headers
+                    .get(header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                let mime = ct.split(';').next().unwrap_or_default().trim();
+                let text = self.render(&text);
+                profile.body = Some(match mime {
+                    n if n == mime::APPLICATION_JSON || n.ends_with("+json") => json_body(&text)?,
+                    n if n == mime::APPLICATION_WWW_FORM_URLENCODED => Value::Object(
+                        serde_urlencoded::from_str::<Vec<(String, String)>>(&text)?
+                            .into_iter()
+                            .map(|(k, v)| (k, Value::String(v)))
+                            .collect(),
+                    ),
+                    _ => Value::String(text),
+                });
+            }
+            DraftBody::Form(fields) => {
+                profile.body = Some(self.object(&fields));
+                profile.headers.insert(
+                    header::CONTENT_TYPE,
+                    content_type(mime::APPLICATION_WWW_FORM_URLENCODED.as_ref())?,
+                );
+            }
+            DraftBody::Multipart { fields, files } => {
+                if !fields.is_empty() {
+                    profile.body = Some(self.object(&fields));
+                }
+                for (k, path) in files {
+                    let part = FilePart {
+                        path: self.render(&path),
+                        filename: None,
+                        content_type: None,
+                    };
+                    profile.files.insert(self.render(&k), part);
+                }
+                profile.headers.insert(
+                    header::CONTENT_TYPE,
+                    content_type(mime::MULTIPART_FORM_DATA.as_ref())?,
+                );
+            }
+            DraftBody::Graphql { query, variables } => {
+                let variables = match self.render(&variables) {
+                    v if v.trim().is_empty() => None,
+                    v => Some(json_body(&v)?),
+                };
+                profile.graphql = Some(GraphqlRequest {
+                    query: Some(self.render(&query)),
+                    variables,
+                    ..Default::default()
+                });
+            }
+            DraftBody::File(path) => profile.body_file = Some(self.render(&path)),
+        }
+        Ok(profile)
+    }
+
+    fn object(&mut self, fields: &[(String, String)]) -> Value {
+        Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (self.render(k), Value::String(self.render(v))))
+                .collect(),
+        )
+    }
+
+    fn finish(mut self) -> (ReqConfig, Vec<String>) {
+        if !self.missing.is_empty() {
+            let names: Vec<_> = self.missing.iter().map(|v| v.as_str()).collect();
+            self.warnings.push(format!(
+                "undefined variables are kept as {{{{name}}}}: {}",
+                names.join(", ")
+            ));
+        }
+        (ReqConfig::new(self.profiles), self.warnings)
+    }
+}
+
+/// A json body, unquoted `{{name}}` placeholders are quoted so they're rendered as json values
+fn json_body(text: &str) -> Result<Value> {
+    if let Ok(value) = serde_json::from_str(text) {
+        return Ok(value);
+    }
+    let re = Regex::new(r#"(^|[^"])(\{\{[^}]+\}\})"#)?;
+    let quoted = re.replace_all(text, r#"$1"$2""#);
+    serde_json::from_str(&quoted).map_err(|e| anyhow!("invalid json body: {}", e))
+}
+
+/// A profile name like `todos_get_todo`
+fn slug(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Method;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let collection: PostmanCollection = std::fs::read_to_string("fixtures/todo.postman.json")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let env = r#"{"values": [{"key": "token", "value": "t0", "enabled": true}]}"#;
+        let (config, warnings) = collection.to_req_config(Some(env)).unwrap();
+        let mut names: Vec<_> = config.profiles.keys().map(|v| v.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["todos_create_todo", "todos_get_todo"]);
+
+        let get = &config.profiles["todos_get_todo"];
+        assert_eq!(
+            get.url.as_str(),
+            "https://jsonplaceholder.typicode.com/todos/1"
+        );
+        assert_eq!(get.params, Some(json!({"a": "1"})));
+        assert_eq!(get.headers["authorization"], "Bearer t0");
+
+        let create = &config.profiles["todos_create_todo"];
+        assert_eq!(create.method, Method::POST);
+        assert_eq!(
+            create.body,
+            Some(json!({"title": "hi", "userId": "{{userId}}"}))
+        );
+        assert_eq!(create.headers["authorization"], "Basic dXNlcjpwYXNz");
+        assert_eq!(
+            warnings,
+            vec![
+                "Todos/Create todo: test script is not supported",
+                "undefined variables are kept as {{name}}: userId",
+            ]
+        );
+    }
+
+    #[test]
+    fn t2() {
+        let export: InsomniaExport = std::fs::read_to_string("fixtures/todo.insomnia.json")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let (config, warnings) = export.to_req_config(Some("staging")).unwrap();
+        let get = &config.profiles["todos_get_todo"];
+        assert_eq!(get.url.as_str(), "https://staging.example.com/todos/1");
+        assert_eq!(get.params, Some(json!({"a": "1"})));
+        assert_eq!(get.headers["x-team"], "core");
+        let form = &config.profiles["todos_create_todo"];
+        assert_eq!(form.body, Some(json!({"title": "hi"})));
+        assert_eq!(warnings, vec!["Stream: websocket_request is not supported"]);
+        assert!(export.to_req_config(Some("prod")).is_err());
+    }
+
+    #[test]
+    fn t3() {
+        let collection: PostmanCollection = r#"{"item": [
+            {"name": "获取", "request": {"url": {
+                "raw": "https://example.com/users/:userId/todos/:id?a=:x",
+                "variable": [{"key": "userId", "value": "7"}, {"key": "id", "value": ""}]
+            }}},
+            {"name": "Get", "request": "http://localhost:8080/todos/:id"}
+        ]}"#
+        .parse()
+        .unwrap();
+        let (config, warnings) = collection.to_req_config(None).unwrap();
+        let todo = &config.profiles["request_1"];
+        assert_eq!(todo.url.as_str(), "https://example.com/users/7/todos/:id");
+        assert_eq!(todo.params, Some(json!({"a": ":x"})));
+        assert_eq!(
+            config.profiles["get"].url.as_str(),
+            "http://localhost:8080/todos/:id"
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "获取: path variable :id is kept as is",
+                "Get: path variable :id is kept as is",
+            ]
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use super::ValidateConfig;
+use crate::{JsonPath, PathSegment};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Leniency when two json bodies are compared, values judged equal show no diff
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Compare {
+    /// numbers at a json path may differ by an absolute or relative amount
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub tolerance: Vec<Tolerance>,
+    /// `"1"` equals `1`
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub numeric_strings: bool,
+    /// a `null` field equals a missing one
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub null_as_missing: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Tolerance {
+    /// json path like `price` or `items[*].score`
+    pub path: String,
+    /// like `0.01`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub abs: Option<f64>,
+    /// a fraction of the larger value, like `0.001`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rel: Option<f64>,
+}
+
+impl Tolerance {
+    fn accepts(&self, a: f64, b: f64) -> bool {
+        let diff = (a - b).abs();
+        diff == 0.0
+            || self.abs.is_some_and(|abs| diff <= abs)
+            || self
+                .rel
+                .is_some_and(|rel| diff <= rel * a.abs().max(b.abs()))
+    }
+}
+
+impl Compare {
+    /// Make the values of `b` that are equal to those of `a` identical to them
+    pub fn reconcile(&self, a: &mut Value, b: &mut Value) -> Result<()> {
+        if self.null_as_missing {
+            remove_nulls(a);
+            remove_nulls(b);
+        }
+        let tolerances = self
+            .tolerance
+            .iter()
+            .map(|t| Ok((t.path.parse::<JsonPath>()?, t)))
+            .collect::<Result<Vec<_>>>()?;
+        self.walk(a, b, &mut vec![], &tolerances);
+        Ok(())
+    }
+
+    fn walk(
+        &self,
+        a: &mut Value,
+        b: &mut Value,
+        path: &mut Vec<PathSegment>,
+        tolerances: &[(JsonPath, &Tolerance)],
+    ) {
+        match (a, b) {
+            (Value::Object(a), Value::Object(b)) => {
+                for (k, v) in a.iter_mut() {
+                    if let Some(other) = b.get_mut(k) {
+                        path.push(PathSegment::Key(k.clone()));
+                        self.walk(v, other, path, tolerances);
+                        path.pop();
+                    }
+                }
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                for (i, (v, other)) in a.iter_mut().zip(b.iter_mut()).enumerate() {
+                    path.push(PathSegment::Index(i));
+                    self.walk(v, other, path, tolerances);
+                    path.pop();
+                }
+            }
+            (a, b) if a != b => {
+                let (x, y) = match (self.number(a), self.number(b)) {
+                    (Some(x), Some(y)) => (x, y),
+                    _ => return,
+                };
+                let tolerance = tolerances.iter().find(|(p, _)| p.matches(path));
+                let equal = match tolerance {
+                    Some((_, t)) => t.accepts(x, y),
+                    None => self.numeric_strings && x == y,
+                };
+                if equal {
+                    *b = a.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn number(&self, value: &Value) -> Option<f64> {
+        match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) if self.numeric_strings => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+fn remove_nulls(value: &mut Value) {
+    match value {
+        Value::Object(obj) => {
+            obj.retain(|_, v| !v.is_null());
+            obj.values_mut().for_each(remove_nulls);
+        }
+        Value::Array(arr) => arr.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+impl ValidateConfig for Compare {
+    fn validate(&self) -> Result<()> {
+        for t in &self.tolerance {
+            t.path
+                .parse::<JsonPath>()
+                .with_context(|| format!("tolerance error [{}]", t.path))?;
+            let amounts = [t.abs, t.rel];
+            if amounts.iter().all(|v| v.is_none()) || amounts.iter().flatten().any(|v| *v < 0.0) {
+                return Err(anyhow!(
+                    "tolerance error [{}]\n a non-negative abs or rel is required",
+                    t.path
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let compare: Compare = serde_yaml::from_str(
+            r#"
+tolerance:
+  - path: price
+    abs: 0.01
+  - path: items[*].score
+    rel: 0.001
+numeric_strings: true
+null_as_missing: true
+"#,
+        )
+        .unwrap();
+        assert!(compare.validate().is_ok());
+        let mut a = json!({
+            "price": 9.99,
+            "id": 1,
+            "items": [{"score": 1000.0}, {"score": 1.0}],
+            "note": null,
+            "total": 20
+        });
+        let mut b = json!({
+            "price": 9.991,
+            "id": "1",
+            "items": [{"score": 1000.5}, {"score": 1.5}],
+            "total": 21
+        });
+        compare.reconcile(&mut a, &mut b).unwrap();
+        assert_eq!(
+            b,
+            json!({
+                "price": 9.99,
+                "id": 1,
+                "items": [{"score": 1000.0}, {"score": 1.5}],
+                "total": 21
+            })
+        );
+        assert_eq!(a.get("note"), None);
+
+        let strict = Compare::default();
+        let (mut a, mut b) = (json!({"id": 1}), json!({"id": "1"}));
+        strict.reconcile(&mut a, &mut b).unwrap();
+        assert_ne!(a, b);
+        let invalid: Compare = serde_yaml::from_str("tolerance: [{path: price}]").unwrap();
+        assert!(invalid.validate().is_err());
+    }
+}
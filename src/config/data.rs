@@ -0,0 +1,83 @@
+use crate::Variables;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::path::Path;
+
+/// Load variables from a csv file with a header row or a jsonl file of objects,
+/// each row is titled like `row 2 (id=5, q=x)`
+pub fn load_data(path: &str) -> Result<Vec<(String, Variables)>> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| anyhow!("read data {} error: {}", path, e))?;
+    let ext = Path::new(path)
+        .extension()
+        .map(|v| v.to_string_lossy().to_lowercase());
+    let rows = match ext.as_deref() {
+        Some("csv") => parse_csv(&content)?,
+        Some("jsonl") | Some("ndjson") => parse_jsonl(&content)?,
+        _ => return Err(anyhow!("data {} must be a .csv or .jsonl file", path)),
+    };
+    Ok(rows
+        .into_iter()
+        .enumerate()
+        .map(|(idx, row)| {
+            let values: Vec<_> = row
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, crate::template::value_to_string(v)))
+                .collect();
+            let title = format!("row {} ({})", idx + 1, values.join(", "));
+            (title, row.into_iter().collect())
+        })
+        .collect())
+}
+
+/// csv cells are json if they parse as json, strings otherwise
+fn parse_csv(content: &str) -> Result<Vec<Vec<(String, Value)>>> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let headers = reader.headers()?.clone();
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let row = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(k, v)| {
+                let v = v.parse().unwrap_or_else(|_| Value::String(v.to_string()));
+                (k.to_string(), v)
+            })
+            .collect();
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn parse_jsonl(content: &str) -> Result<Vec<Vec<(String, Value)>>> {
+    let mut rows = vec![];
+    for (idx, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(Value::Object(obj)) => rows.push(obj.into_iter().collect()),
+            _ => return Err(anyhow!("line {} is not a json object", idx + 1)),
+        }
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let rows = load_data("fixtures/cases.csv").unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0, "row 1 (id=1, q=hello world)");
+        assert_eq!(rows[0].1["id"], json!(1));
+        let rows = load_data("fixtures/cases.jsonl").unwrap();
+        assert_eq!(rows[1].0, "row 2 (id=2, q=b)");
+        assert!(load_data("fixtures/test.yml").is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use super::{ResponseExt, ValidateConfig};
+use crate::{
+    schema::{type_matches, type_name},
+    template::value_to_string,
+    JsonPath,
+};
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+const TYPES: &[&str] = &[
+    "null", "boolean", "integer", "number", "string", "array", "object",
+];
+
+/// Assertions on the response of a profile, checked by `xreq test`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Expect {
+    /// a code like `200`, or a range like `2xx` or `200-204`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub status: Option<ExpectStatus>,
+    /// header name to its expected value or a check
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub headers: BTreeMap<String, Assertion>,
+    /// json path like `data.items[0].id` to its expected value or a check
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub json: BTreeMap<String, Assertion>,
+    /// regex the body must match
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ExpectStatus {
+    Code(u16),
+    Range(String),
+}
+
+/// An expected value, or a check like `{exists: true}` or `{matches: "^W/"}`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Assertion {
+    Check(Check),
+    Equals(Value),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Check {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub exists: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub equals: Option<Value>,
+    /// a substring, an array item, or a subset of an object
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub contains: Option<Value>,
+    /// a json type like `string` or `integer`
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none", default)]
+    pub kind: Option<String>,
+    /// regex the value must match
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub matches: Option<String>,
+}
+
+impl ExpectStatus {
+    /// The inclusive range of codes
+    fn range(&self) -> Result<(u16, u16)> {
+        match self {
+            ExpectStatus::Code(code) => Ok((*code, *code)),
+            ExpectStatus::Range(s) => {
+                let s = s.trim();
+                if let Some(n) = s.strip_suffix("xx").or_else(|| s.strip_suffix("XX")) {
+                    let n: u16 = n.parse().map_err(|_| anyhow!("invalid status: {}", s))?;
+                    return Ok((n * 100, n * 100 + 99));
+                }
+                let (from, to) = s.split_once('-').unwrap_or((s, s));
+                let parse = |v: &str| -> Result<u16> {
+                    v.trim()
+                        .parse()
+                        .map_err(|_| anyhow!("invalid status: {}", s))
+                };
+                Ok((parse(from)?, parse(to)?))
+            }
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            ExpectStatus::Code(code) => code.to_string(),
+            ExpectStatus::Range(s) => s.clone(),
+        }
+    }
+}
+
+impl Assertion {
+    /// Why the value doesn't pass, `text` compares values as strings, like headers
+    fn failure(&self, actual: Option<&Value>, text: bool) -> Result<Option<String>> {
+        let check = match self {
+            Assertion::Equals(expected) => {
+                return Check {
+                    equals: Some(expected.clone()),
+                    ..Default::default()
+                }
+                .failure(actual, text)
+            }
+            Assertion::Check(check) => check,
+        };
+        check.failure(actual, text)
+    }
+}
+
+impl Check {
+    fn failure(&self, actual: Option<&Value>, text: bool) -> Result<Option<String>> {
+        let actual = match (actual, self.exists) {
+            (None, Some(false)) => return Ok(None),
+            (None, _) => return Ok(Some("missing".to_string())),
+            (Some(v), Some(false)) => return Ok(Some(format!("expected to be absent, got {}", v))),
+            (Some(v), _) => v,
+        };
+        if let Some(expected) = self.equals.as_ref() {
+            let same = match text {
+                true => value_to_string(expected) == value_to_string(actual),
+                false => expected == actual,
+            };
+            if !same {
+                return Ok(Some(format!("expected {}, got {}", expected, actual)));
+            }
+        }
+        if let Some(expected) = self.contains.as_ref() {
+            if !contains(actual, expected) {
+                return Ok(Some(format!("expected {} to contain {}", actual, expected)));
+            }
+        }
+        if let Some(kind) = self.kind.as_ref() {
+            if !type_matches(kind, actual) {
+                return Ok(Some(format!(
+                    "expected {}, got {}",
+                    kind,
+                    type_name(actual)
+                )));
+            }
+        }
+        if let Some(re) = self.matches.as_ref() {
+            if !Regex::new(re)?.is_match(&value_to_string(actual)) {
+                return Ok(Some(format!("{} doesn't match {}", actual, re)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::String(s), v) => s.contains(&value_to_string(v)),
+        (Value::Array(items), v) => items.contains(v),
+        (Value::Object(obj), Value::Object(sub)) => sub.iter().all(|(k, v)| obj.get(k) == Some(v)),
+        _ => false,
+    }
+}
+
+impl Expect {
+    /// Every failed assertion, like `status: expected 2xx, got 404`
+    pub async fn check(&self, res: ResponseExt, latency: Duration) -> Result<Vec<String>> {
+        let res = res.into_inner();
+        let mut failures = vec![];
+        if let Some(status) = self.status.as_ref() {
+            let (from, to) = status.range()?;
+            let code = res.status().as_u16();
+            if code < from || code > to {
+                failures.push(format!("status: expected {}, got {}", status.text(), code));
+            }
+        }
+        for (name, assertion) in &self.headers {
+            let actual = res
+                .headers()
+                .get(name)
+                .map(|v| Value::String(String::from_utf8_lossy(v.as_bytes()).to_string()));
+            if let Some(failure) = assertion.failure(actual.as_ref(), true)? {
+                failures.push(format!("header {}: {}", name, failure));
+            }
+        }
+        if let Some(max) = self.max_latency_ms {
+            let ms = latency.as_millis();
+            if ms > max as u128 {
+                failures.push(format!("latency: {}ms is over {}ms", ms, max));
+            }
+        }
+
+        let body = res.text().await?;
+        if let Some(re) = self.body.as_ref() {
+            if !Regex::new(re)?.is_match(&body) {
+                failures.push(format!("body: doesn't match {}", re));
+            }
+        }
+        if !self.json.is_empty() {
+            match serde_json::from_str::<Value>(&body) {
+                Ok(json) => {
+                    for (path, assertion) in &self.json {
+                        let parsed: JsonPath = path.parse()?;
+                        if let Some(failure) = assertion.failure(parsed.get(&json), false)? {
+                            failures.push(format!("json {}: {}", parsed, failure));
+                        }
+                    }
+                }
+                Err(_) => failures.push("json: body is not json".to_string()),
+            }
+        }
+        Ok(failures)
+    }
+}
+
+impl ValidateConfig for Expect {
+    fn validate(&self) -> Result<()> {
+        if let Some(status) = self.status.as_ref() {
+            status.range()?;
+        }
+        if let Some(re) = self.body.as_ref() {
+            Regex::new(re).with_context(|| format!("expect body error [{}]", re))?;
+        }
+        let checks = self.headers.iter().chain(self.json.iter());
+        for (key, assertion) in checks {
+            if let Assertion::Check(check) = assertion {
+                if let Some(re) = check.matches.as_ref() {
+                    Regex::new(re).with_context(|| format!("expect error [{}]", key))?;
+                }
+                if let Some(kind) = check.kind.as_ref() {
+                    if !TYPES.contains(&kind.as_str()) {
+                        return Err(anyhow!("expect error [{}]\n unknown type {}", key, kind));
+                    }
+                }
+            }
+        }
+        for path in self.json.keys() {
+            path.parse::<JsonPath>()
+                .with_context(|| format!("expect json error [{}]", path))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::mock;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn t1() {
+        let _m = mock("GET", "/expect/todo")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_header("etag", "W/\"123\"")
+            .with_body(r#"{"id": 1, "title": "buy milk", "tags": ["a", "b"]}"#)
+            .create();
+        let expect: Expect = serde_yaml::from_str(
+            r#"
+status: 2xx
+headers:
+  etag:
+    matches: ^W/
+  x-missing:
+    exists: false
+json:
+  id: 1
+  title:
+    contains: milk
+  tags:
+    contains: b
+    type: array
+  $.user:
+    exists: true
+body: buy
+max_latency_ms: 1000
+"#,
+        )
+        .unwrap();
+        assert!(expect.validate().is_ok());
+        assert_eq!(expect.json["id"], Assertion::Equals(json!(1)));
+
+        let url = format!("{}/expect/todo", mockito::server_url());
+        let res: ResponseExt = reqwest::get(&url).await.unwrap().into();
+        let failures = expect.check(res, Duration::from_millis(10)).await.unwrap();
+        assert_eq!(failures, vec!["json $.user: missing"]);
+
+        let strict: Expect = serde_yaml::from_str("status: 200\nmax_latency_ms: 5").unwrap();
+        let res: ResponseExt = reqwest::get(&url).await.unwrap().into();
+        let failures = strict.check(res, Duration::from_millis(10)).await.unwrap();
+        assert_eq!(
+            failures,
+            vec!["status: expected 200, got 201", "latency: 10ms is over 5ms"]
+        );
+        assert!(serde_yaml::from_str::<Expect>("status: abc")
+            .unwrap()
+            .validate()
+            .is_err());
+    }
+}
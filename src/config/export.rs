@@ -0,0 +1,237 @@
+use super::{FilePart, RequestProfile};
+use crate::{template::value_to_string, ExtraArgs};
+use anyhow::{anyhow, Result};
+use reqwest::{cookie::CookieStore, header, Method};
+use serde_json::Value;
+use shell_words::quote;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Text fields and file parts of a multipart body
+type FormParts = (Vec<(String, String)>, BTreeMap<String, FilePart>);
+
+/// Format of an exported request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    Curl,
+    Httpie,
+    /// `.http` file of the VS Code and JetBrains http clients
+    HttpFile,
+}
+
+impl RequestProfile {
+    /// The request that would be sent with `args`, as a command or http file
+    pub fn export(&self, args: &ExtraArgs, format: ExportFormat) -> Result<String> {
+        let (method, url, mut headers, body) = self.prepare(args)?;
+        if let Some(jar) = self.cookie_jar()? {
+            if let Some(cookies) = jar.cookies(&url) {
+                headers.entry(header::COOKIE).or_insert(cookies);
+            }
+        }
+        // multipart bodies are exported as form parts, the tools pick their own boundary
+        let form = match headers.get(header::CONTENT_TYPE).map(|v| v.to_str()) {
+            Some(std::result::Result::Ok(ct))
+                if ct.starts_with("multipart/form-data")
+                    && format != ExportFormat::HttpFile
+                    && self.graphql.is_none()
+                    && self.body_file.is_none() =>
+            {
+                headers.remove(header::CONTENT_TYPE);
+                Some(self.form_parts(args)?)
+            }
+            _ => None,
+        };
+        let body = match form {
+            Some(_) => String::new(),
+            None => {
+                String::from_utf8(body).map_err(|_| anyhow!("only text bodies can be exported"))?
+            }
+        };
+        let headers = headers
+            .iter()
+            .map(|(k, v)| Ok((k.as_str(), v.to_str()?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut output = String::new();
+        match format {
+            ExportFormat::Curl => {
+                write!(&mut output, "curl")?;
+                if method != Method::GET || !body.is_empty() || form.is_some() {
+                    write!(&mut output, " -X {}", method)?;
+                }
+                write!(&mut output, " {}", quote(url.as_str()))?;
+                for (k, v) in headers {
+                    write!(&mut output, " \\\n  -H {}", quote(&format!("{}: {}", k, v)))?;
+                }
+                if !body.is_empty() {
+                    write!(&mut output, " \\\n  --data-raw {}", quote(&body))?;
+                }
+                if let Some((fields, files)) = form.as_ref() {
+                    for (k, v) in fields {
+                        // -F reads a file for values starting with @ or <
+                        let opt = match v.starts_with(['@', '<']) {
+                            true => "--form-string",
+                            false => "-F",
+                        };
+                        write!(
+                            &mut output,
+                            " \\\n  {} {}",
+                            opt,
+                            quote(&format!("{}={}", k, v))
+                        )?;
+                    }
+                    for (k, part) in files {
+                        let part = format!("{}=@{}", k, part.spec());
+                        write!(&mut output, " \\\n  -F {}", quote(&part))?;
+                    }
+                }
+            }
+            ExportFormat::Httpie => {
+                write!(&mut output, "http")?;
+                if form.is_some() {
+                    write!(&mut output, " --multipart")?;
+                }
+                if !body.is_empty() {
+                    write!(&mut output, " --raw {}", quote(&body))?;
+                }
+                write!(&mut output, " {} {}", method, quote(url.as_str()))?;
+                for (k, v) in headers {
+                    write!(&mut output, " \\\n  {}", quote(&format!("{}:{}", k, v)))?;
+                }
+                if let Some((fields, files)) = form.as_ref() {
+                    for (k, v) in fields {
+                        // `=@` reads the value from a file, `=\@` keeps it
+                        let v = match v.starts_with('@') {
+                            true => format!("\\{}", v),
+                            false => v.clone(),
+                        };
+                        write!(&mut output, " \\\n  {}", quote(&format!("{}={}", k, v)))?;
+                    }
+                    // httpie names the part after the file, the filename can't be changed
+                    for (k, part) in files {
+                        let mut arg = format!("{}@{}", k, part.path);
+                        if let Some(ct) = part.content_type.as_ref() {
+                            arg.push_str(&format!(";type={}", ct));
+                        }
+                        write!(&mut output, " \\\n  {}", quote(&arg))?;
+                    }
+                }
+            }
+            ExportFormat::HttpFile => {
+                writeln!(&mut output, "{} {} HTTP/1.1", method, url)?;
+                for (k, v) in headers {
+                    writeln!(&mut output, "{}: {}", k, v)?;
+                }
+                if !body.is_empty() {
+                    write!(&mut output, "\n{}", body)?;
+                }
+            }
+        }
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+        Ok(output)
+    }
+}
+
+impl RequestProfile {
+    /// Text fields and file parts of a multipart/form-data request
+    fn form_parts(&self, args: &ExtraArgs) -> Result<FormParts> {
+        let fields = match self.body_with(args)? {
+            Some(Value::Object(fields)) => fields
+                .iter()
+                .map(|(k, v)| (k.clone(), value_to_string(v)))
+                .collect(),
+            Some(_) => return Err(anyhow!("multipart fields need an object body")),
+            None => vec![],
+        };
+        Ok((fields, self.files_with(args)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::parse_key_val;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let profile: RequestProfile = serde_yaml::from_str(
+            r#"
+method: POST
+url: https://example.com/todos/{id}
+path_params:
+  id: 1
+params:
+  a: 1
+headers:
+  content-type: application/json
+body:
+  title: it's
+"#,
+        )
+        .unwrap();
+        let args: ExtraArgs = vec![
+            parse_key_val("%x-id=7").unwrap(),
+            parse_key_val("@done:=true").unwrap(),
+        ]
+        .into();
+        let curl = profile.export(&args, ExportFormat::Curl).unwrap();
+        assert_eq!(
+            curl,
+            r#"curl -X POST 'https://example.com/todos/1?a=1' \
+  -H 'content-type: application/json' \
+  -H 'x-id: 7' \
+  --data-raw '{"done":true,"title":"it'\''s"}'
+"#
+        );
+        let http = profile.export(&args, ExportFormat::HttpFile).unwrap();
+        assert!(http.starts_with("POST https://example.com/todos/1?a=1 HTTP/1.1\n"));
+        assert!(http.ends_with("x-id: 7\n\n{\"done\":true,\"title\":\"it's\"}\n"));
+        let httpie = profile.export(&args, ExportFormat::Httpie).unwrap();
+        assert!(httpie.starts_with("http --raw '{\"done\""));
+    }
+
+    #[test]
+    fn t2() {
+        let profile: RequestProfile = serde_yaml::from_str(
+            r#"
+method: POST
+url: https://example.com/upload
+body:
+  name: me
+  note: "@home"
+files:
+  avatar:
+    path: fixtures/test.yml
+    content_type: text/yaml
+"#,
+        )
+        .unwrap();
+        let args: ExtraArgs =
+            vec![parse_key_val("+doc=fixtures/todo.har;filename=a b.har").unwrap()].into();
+        let curl = profile.export(&args, ExportFormat::Curl).unwrap();
+        assert_eq!(
+            curl,
+            r#"curl -X POST https://example.com/upload \
+  -F 'name=me' \
+  --form-string 'note=@home' \
+  -F 'avatar=@fixtures/test.yml;type=text/yaml' \
+  -F 'doc=@fixtures/todo.har;filename=a b.har'
+"#
+        );
+        let httpie = profile.export(&args, ExportFormat::Httpie).unwrap();
+        assert_eq!(
+            httpie,
+            r#"http --multipart POST https://example.com/upload \
+  'name=me' \
+  'note=\@home' \
+  'avatar@fixtures/test.yml;type=text/yaml' \
+  doc@fixtures/todo.har
+"#
+        );
+        let http = profile.export(&args, ExportFormat::HttpFile).unwrap();
+        assert!(http.contains("content-type: multipart/form-data; boundary="));
+    }
+}
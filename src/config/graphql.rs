@@ -0,0 +1,135 @@
+use super::ValidateConfig;
+use crate::ArgValue;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// A graphql request, sent as a POST with the standard json envelope
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct GraphqlRequest {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub query: Option<String>,
+    /// `.graphql` file holding the query, instead of `query`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub query_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub variables: Option<Value>,
+    #[serde(
+        rename = "operationName",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub operation_name: Option<String>,
+}
+
+/// How `data` and `errors` of a graphql response are compared
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct GraphqlResponse {
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub skip_data: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub skip_errors: bool,
+    /// only compare the message of each error, not its locations, path or extensions
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub error_messages: bool,
+}
+
+impl GraphqlRequest {
+    /// The json envelope, `variables` overrides the configured variables
+    pub fn body(&self, variables: &[(String, ArgValue)]) -> Result<Value> {
+        let query = match (&self.query, &self.query_file) {
+            (Some(query), _) => query.clone(),
+            (None, Some(file)) => std::fs::read_to_string(file)
+                .map_err(|e| anyhow!("read query file {} error: {}", file, e))?,
+            (None, None) => return Err(anyhow!("graphql query is missing")),
+        };
+        let mut vars = self.variables.clone().unwrap_or_else(|| json!({}));
+        for (k, v) in variables {
+            v.apply(k, &mut vars)?;
+        }
+
+        let mut body = json!({ "query": query });
+        if !vars.as_object().is_some_and(|v| v.is_empty()) {
+            body["variables"] = vars;
+        }
+        if let Some(name) = self.operation_name.as_ref() {
+            body["operationName"] = json!(name);
+        }
+        Ok(body)
+    }
+}
+
+impl GraphqlResponse {
+    pub fn filter(&self, json: &mut Value) {
+        let obj = match json.as_object_mut() {
+            Some(obj) => obj,
+            None => return,
+        };
+        if self.skip_data {
+            obj.remove("data");
+        }
+        if self.skip_errors {
+            obj.remove("errors");
+        } else if self.error_messages {
+            if let Some(Value::Array(errors)) = obj.get_mut("errors") {
+                for err in errors.iter_mut() {
+                    if let Some(message) = err.get("message").cloned() {
+                        *err = message;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl ValidateConfig for GraphqlRequest {
+    fn validate(&self) -> Result<()> {
+        if self.query.is_some() == self.query_file.is_some() {
+            return Err(anyhow!("graphql needs either query or query_file"));
+        }
+        if let Some(vars) = self.variables.as_ref() {
+            if !vars.is_object() {
+                return Err(anyhow!("graphql variables must be an object"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let req: GraphqlRequest = serde_yaml::from_str(
+            r#"
+query: "query Todo($id: ID!) { todo(id: $id) { title } }"
+variables:
+  id: 1
+operationName: Todo
+"#,
+        )
+        .unwrap();
+        req.validate().unwrap();
+        let body = req
+            .body(&[("id".to_string(), ArgValue::Text("2".to_string()))])
+            .unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "query": "query Todo($id: ID!) { todo(id: $id) { title } }",
+                "variables": {"id": 2},
+                "operationName": "Todo"
+            })
+        );
+
+        let profile = GraphqlResponse {
+            error_messages: true,
+            ..Default::default()
+        };
+        let mut res = json!({"data": null, "errors": [{"message": "oops", "locations": []}]});
+        profile.filter(&mut res);
+        assert_eq!(res, json!({"data": null, "errors": ["oops"]}));
+    }
+}
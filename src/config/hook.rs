@@ -0,0 +1,217 @@
+use super::{Capture, ScenarioStep, ValidateConfig};
+use crate::{template::render_shell, CookieJar, Variables};
+use anyhow::{anyhow, Context, Ok, Result};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::process::Command;
+
+/// A setup or teardown step, either a local shell command or an http request
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Hook {
+    Command(CommandHook),
+    Request(Box<ScenarioStep>),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommandHook {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    /// run with `sh -c`, also on windows where `sh` must be on the path, like git bash's.
+    /// `{{name}}` variables are substituted with POSIX shell quoting
+    pub command: String,
+    /// values captured from stdout
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub capture: BTreeMap<String, Capture>,
+}
+
+impl Hook {
+    pub fn title(&self, idx: usize) -> String {
+        match self {
+            Hook::Command(cmd) => match &cmd.name {
+                Some(name) => format!("hook {} [{}]", idx + 1, name),
+                None => format!("hook {} [{}]", idx + 1, cmd.command),
+            },
+            Hook::Request(step) => step.title(idx).replacen("step", "hook", 1),
+        }
+    }
+
+    pub async fn run(&self, vars: &mut Variables, jar: Option<Arc<CookieJar>>) -> Result<()> {
+        match self {
+            Hook::Command(cmd) => cmd.run(vars).await,
+            Hook::Request(step) => {
+                let jar = match jar {
+                    Some(jar) => {
+                        step.request.seed_cookies(&jar)?;
+                        Some(jar)
+                    }
+                    None => step.request.cookie_jar()?,
+                };
+                let (_, res) = step.run(&Default::default(), vars, jar).await?;
+                let status = res.into_inner().status();
+                if status.is_client_error() || status.is_server_error() {
+                    return Err(anyhow!("request failed with status {}", status));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl CommandHook {
+    pub async fn run(&self, vars: &mut Variables) -> Result<()> {
+        let command = render_shell(&self.command, vars)?;
+        // cmd.exe has no single quotes, so windows runs `sh` too
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .output()
+            .await
+            .context("run sh error")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "command failed with {}\n{}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        for (name, capture) in &self.capture {
+            let value = capture
+                .extract(&HeaderMap::new(), &output.stdout)?
+                .ok_or_else(|| anyhow!("capture {} not found", name))?;
+            vars.insert(name.clone(), value);
+        }
+        Ok(())
+    }
+}
+
+/// Run hooks in order, stopping at the first failure
+pub async fn run_hooks(
+    hooks: &[Hook],
+    vars: &mut Variables,
+    jar: Option<Arc<CookieJar>>,
+) -> Result<()> {
+    for (idx, hook) in hooks.iter().enumerate() {
+        hook.run(vars, jar.clone())
+            .await
+            .with_context(|| hook.title(idx))?;
+    }
+    Ok(())
+}
+
+impl ValidateConfig for Hook {
+    fn validate(&self) -> Result<()> {
+        match self {
+            Hook::Command(cmd) => {
+                for (name, capture) in &cmd.capture {
+                    if let Capture::Header { .. } = capture {
+                        return Err(anyhow!("capture {}: commands have no headers", name));
+                    }
+                    capture
+                        .validate()
+                        .with_context(|| format!("capture {}", name))?;
+                }
+                Ok(())
+            }
+            Hook::Request(step) => step.validate(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::{mock, Matcher};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{DiffProfile, ExtraArgs};
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn t1() {
+        let hook: Hook = serde_yaml::from_str(
+            r#"
+command: >-
+  echo '{"token": "{{user}}-abc"}'
+capture:
+  token:
+    json: $.token
+"#,
+        )
+        .unwrap();
+        hook.validate().unwrap();
+
+        let mut vars = Variables::new();
+        vars.insert("user".to_string(), json!("tom"));
+        hook.run(&mut vars, None).await.unwrap();
+        assert_eq!(vars["token"], json!("tom-abc"));
+
+        vars.insert("user".to_string(), json!("x; echo pwned"));
+        let hook: Hook = serde_yaml::from_str(
+            r#"
+command: echo {{user}}
+capture:
+  echoed:
+    regex: (.+)
+"#,
+        )
+        .unwrap();
+        hook.run(&mut vars, None).await.unwrap();
+        assert_eq!(vars["echoed"], json!("x; echo pwned"));
+
+        let hook: Hook = serde_yaml::from_str("command: exit 3").unwrap();
+        assert!(hook.run(&mut vars, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn t2() {
+        let _login = mock("POST", "/hook/login")
+            .with_header("set-cookie", "session=abc; Path=/")
+            .create();
+        let me = mock("GET", "/hook/me")
+            .match_header("cookie", "session=abc")
+            .with_body("me")
+            .expect(2)
+            .create();
+        let url = mockito::server_url();
+        let profile: DiffProfile = serde_yaml::from_str(&format!(
+            r#"
+setup:
+  - name: login
+    url: {url}/hook/login
+    method: POST
+req1:
+  url: {url}/hook/me
+req2:
+  url: {url}/hook/me
+"#
+        ))
+        .unwrap();
+        profile.diff(ExtraArgs::default()).await.unwrap();
+        me.assert();
+    }
+
+    #[tokio::test]
+    async fn t3() {
+        let _a = mock("GET", "/hook/a")
+            .with_header("set-cookie", "side=1; Path=/")
+            .create();
+        let b = mock("GET", "/hook/b")
+            .match_header("cookie", Matcher::Missing)
+            .expect(1)
+            .create();
+        let url = mockito::server_url();
+        let profile: DiffProfile = serde_yaml::from_str(&format!(
+            r#"
+req1:
+  url: {url}/hook/a
+req2:
+  url: {url}/hook/b
+"#
+        ))
+        .unwrap();
+        profile.diff(ExtraArgs::default()).await.unwrap();
+        b.assert();
+    }
+}
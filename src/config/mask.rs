@@ -0,0 +1,113 @@
+use super::ValidateConfig;
+use crate::JsonPath;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Replace what a regex matches in volatile values, like an id inside a message.
+/// Applies to the `headers` or `json` paths given, or to the body text if neither is
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Mask {
+    pub pattern: String,
+    /// `***` by default, can refer to groups like `$1`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub replace: Option<String>,
+    /// names of the headers to mask
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub headers: Vec<String>,
+    /// json paths like `data.items[*].message` of the string values to mask
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub json: Vec<String>,
+}
+
+impl Mask {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the mask applies to the body text
+    pub fn is_body(&self) -> bool {
+        self.headers.is_empty() && self.json.is_empty()
+    }
+
+    pub fn is_header(&self, name: &str) -> bool {
+        self.headers.iter().any(|h| h.eq_ignore_ascii_case(name))
+    }
+
+    pub fn apply(&self, text: &str) -> Result<String> {
+        let replace = self.replace.as_deref().unwrap_or("***");
+        Ok(Regex::new(&self.pattern)?
+            .replace_all(text, replace)
+            .into_owned())
+    }
+
+    /// Mask the string values at the json paths
+    pub fn apply_json(&self, json: &mut Value) -> Result<()> {
+        for path in &self.json {
+            for value in path.parse::<JsonPath>()?.select_mut(json) {
+                if let Value::String(s) = value {
+                    *s = self.apply(s)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ValidateConfig for Mask {
+    fn validate(&self) -> Result<()> {
+        Regex::new(&self.pattern)?;
+        for path in &self.json {
+            path.parse::<JsonPath>()
+                .with_context(|| format!("json path error [{}]", path))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let mask: Mask = serde_yaml::from_str(
+            r#"
+pattern: request [0-9a-f]+
+replace: request <id>
+json: [message, "items[*].note"]
+"#,
+        )
+        .unwrap();
+        assert!(mask.validate().is_ok());
+        assert!(!mask.is_body());
+        let mut body = json!({
+            "message": "request 8f3a failed",
+            "items": [{"note": "request 01 ok"}, {"note": 1}],
+            "other": "request 8f3a"
+        });
+        mask.apply_json(&mut body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "message": "request <id> failed",
+                "items": [{"note": "request <id> ok"}, {"note": 1}],
+                "other": "request 8f3a"
+            })
+        );
+
+        let mask = Mask {
+            headers: vec!["ETag".to_string()],
+            ..Mask::new(r#"W/"(\d+)""#)
+        };
+        assert!(mask.is_header("etag"));
+        assert_eq!(mask.apply(r#"W/"123""#).unwrap(), "***");
+        assert!(Mask::new("(").validate().is_err());
+    }
+}
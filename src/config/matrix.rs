@@ -0,0 +1,95 @@
+use crate::{template::value_to_string, Variables};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Values of `{{name}}` variables, a profile runs once per combination
+pub type Matrix = BTreeMap<String, Vec<Value>>;
+
+/// Every combination of the matrix values, a single empty combination for an empty matrix
+pub fn expand(matrix: &Matrix) -> Vec<Variables> {
+    let mut combos = vec![Variables::new()];
+    for (name, values) in matrix {
+        combos = combos
+            .into_iter()
+            .flat_map(|vars| {
+                values.iter().map(move |v| {
+                    let mut vars = vars.clone();
+                    vars.insert(name.clone(), v.clone());
+                    vars
+                })
+            })
+            .collect();
+    }
+    combos
+}
+
+/// A combination as `id=1, lang=en`, in the order of the matrix
+pub fn title(matrix: &Matrix, vars: &Variables) -> String {
+    matrix
+        .keys()
+        .filter_map(|k| vars.get(k).map(|v| format!("{}={}", k, value_to_string(v))))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Every data row with every matrix combination, titled like `row 1 (id=5) lang=en`
+pub fn runs(matrix: &Matrix, rows: &[(String, Variables)]) -> Vec<(String, Variables)> {
+    let default = [(String::new(), Variables::new())];
+    let rows = match rows.is_empty() {
+        true => &default[..],
+        false => rows,
+    };
+    let combos = expand(matrix);
+    let mut runs = vec![];
+    for (row_title, row) in rows {
+        for combo in &combos {
+            let combo_title = title(matrix, combo);
+            let title = match (row_title.is_empty(), combo_title.is_empty()) {
+                (_, true) => row_title.clone(),
+                (true, false) => combo_title,
+                (false, false) => format!("{} {}", row_title, combo_title),
+            };
+            let mut vars = row.clone();
+            vars.extend(combo.clone());
+            runs.push((title, vars));
+        }
+    }
+    runs
+}
+
+pub fn validate(matrix: &Matrix) -> Result<()> {
+    for (name, values) in matrix {
+        if values.is_empty() {
+            return Err(anyhow!("matrix {} has no values", name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let matrix: Matrix = serde_yaml::from_str("id: [1, 2, 3]\nlang: [en, fr]").unwrap();
+        let combos = expand(&matrix);
+        assert_eq!(combos.len(), 6);
+        assert_eq!(title(&matrix, &combos[1]), "id=1, lang=fr");
+        assert_eq!(combos[5]["id"], json!(3));
+        assert_eq!(expand(&Matrix::new()), vec![Variables::new()]);
+        assert!(validate(&serde_yaml::from_str("id: []").unwrap()).is_err());
+
+        let rows = vec![("row 1 (q=a)".to_string(), Variables::new())];
+        let runs = runs(&matrix, &rows);
+        assert_eq!(runs.len(), 6);
+        assert_eq!(runs[0].0, "row 1 (q=a) id=1, lang=en");
+        assert_eq!(
+            super::runs(&Matrix::new(), &[]),
+            vec![(String::new(), Variables::new())]
+        );
+    }
+}
@@ -1,19 +1,50 @@
+mod compare;
+mod data;
+mod expect;
+mod export;
+mod graphql;
+mod hook;
+mod mask;
+mod matrix;
+mod multipart;
+mod query;
+mod scenario;
+mod unordered;
 mod xdiff;
 mod xreq;
 
-use crate::ExtraArgs;
-use anyhow::{Ok, Result};
+use crate::{
+    cookie::get_set_cookie_text,
+    template::{render_json, render_path, render_str, render_url, value_to_string},
+    utils::diff_text,
+    CookieJar, ExtraArgs, Variables,
+};
+use anyhow::{Context, Ok, Result};
 use async_trait::async_trait;
 use reqwest::{
     header::{self, HeaderMap, HeaderName, HeaderValue},
     Method, Response,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt::Write;
 use std::str::FromStr;
+use std::sync::Arc;
 use tokio::fs;
 use url::Url;
 
+pub use compare::{Compare, Tolerance};
+pub use data::load_data;
+pub use expect::{Assertion, Check, Expect, ExpectStatus};
+pub use export::ExportFormat;
+pub use graphql::{GraphqlRequest, GraphqlResponse};
+pub use hook::{CommandHook, Hook};
+pub use mask::Mask;
+pub use matrix::Matrix;
+pub use multipart::FilePart;
+pub use query::QueryFormat;
+pub use scenario::{Capture, DiffScenario, Scenario, ScenarioStep};
+pub use unordered::Unordered;
 pub use xdiff::{DiffConfig, DiffProfile, ResponseProfile};
 pub use xreq::ReqConfig;
 
@@ -47,8 +78,14 @@ pub struct RequestProfile {
     #[serde(with = "http_serde::method", default)]
     pub method: Method,
     pub url: Url,
+    /// defaults for `{name}` placeholders in the url path
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub path_params: BTreeMap<String, serde_json::Value>,
     #[serde(skip_serializing_if = "empty_json_value", default)]
     pub params: Option<serde_json::Value>,
+    /// how arrays in params are encoded, `repeat`, `brackets` or `comma`
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub query_format: QueryFormat,
     #[serde(
         skip_serializing_if = "HeaderMap::is_empty",
         with = "http_serde::header_map",
@@ -57,35 +94,102 @@ pub struct RequestProfile {
     pub headers: HeaderMap,
     #[serde(skip_serializing_if = "empty_json_value", default)]
     pub body: Option<serde_json::Value>,
+    /// file sent as the body verbatim, instead of `body`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub body_file: Option<String>,
+    /// file parts of a multipart/form-data body, text fields come from `body`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub files: BTreeMap<String, FilePart>,
+    /// send a graphql query instead of `body`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub graphql: Option<GraphqlRequest>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cookies: Option<CookieProfile>,
+    /// send once per combination, values are used as `{{name}}` variables
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub matrix: Matrix,
+    /// assertions on the response, checked by `xreq test`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub expect: Option<Expect>,
+}
+
+/// Opt-in cookie jar for a profile, optionally seeded with cookies
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct CookieProfile {
+    /// cookies sent to the request url
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub values: BTreeMap<String, String>,
+    /// Netscape cookies file
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub file: Option<String>,
 }
 
 #[derive(Debug)]
 pub struct ResponseExt(Response);
 
+impl From<Response> for ResponseExt {
+    fn from(res: Response) -> Self {
+        Self(res)
+    }
+}
+
 impl ResponseExt {
     pub fn into_inner(self) -> Response {
         self.0
     }
 
     pub async fn filter_text(self, profile: &ResponseProfile) -> Result<String> {
-        let res = self.0;
-        let mut output = String::new();
+        let (head, body) = self.filter_parts(profile).await?;
+        Ok(format!("{}{}", head, body.text(profile)?))
+    }
 
-        write!(&mut output, "{}", get_status_text(&res)?)?;
+    /// Diff of two responses, json bodies are compared with the leniency of the profile
+    pub async fn diff(self, other: ResponseExt, profile: &ResponseProfile) -> Result<String> {
+        let (head1, mut body1) = self.filter_parts(profile).await?;
+        let (head2, mut body2) = other.filter_parts(profile).await?;
+        if let (Some(json1), Some(json2)) = (body1.json.as_mut(), body2.json.as_mut()) {
+            profile.compare.reconcile(json1, json2)?;
+        }
+        let t1 = format!("{}{}", head1, body1.text(profile)?);
+        let t2 = format!("{}{}", head2, body2.text(profile)?);
+        diff_text(&t1, &t2)
+    }
 
-        write!(
-            &mut output,
-            "{}",
-            get_header_text(&res, &profile.skip_headers)?
-        )?;
+    /// The status and header text, and the filtered body
+    async fn filter_parts(self, profile: &ResponseProfile) -> Result<(String, FilteredBody)> {
+        let res = self.0;
+        let mut head = String::new();
+        write!(&mut head, "{}", get_status_text(&res)?)?;
+        write!(&mut head, "{}", get_filtered_header_text(&res, profile)?)?;
+        Ok((head, FilteredBody::read(res, profile).await?))
+    }
 
-        write!(
-            &mut output,
-            "{}",
-            get_body_text(res, &profile.skip_body).await?
-        )?;
+    /// Read the body to capture values into `vars`, the response stays usable afterwards
+    pub async fn capture(
+        self,
+        captures: &BTreeMap<String, Capture>,
+        vars: &mut Variables,
+    ) -> Result<Self> {
+        if captures.is_empty() {
+            return Ok(self);
+        }
+        let res = self.0;
+        let mut builder = http::Response::builder()
+            .status(res.status())
+            .version(res.version());
+        for (k, v) in res.headers() {
+            builder = builder.header(k, v);
+        }
+        let headers = res.headers().clone();
+        let body = res.bytes().await?;
+        for (name, capture) in captures {
+            let value = capture
+                .extract(&headers, &body)?
+                .ok_or_else(|| anyhow::anyhow!("capture {} not found", name))?;
+            vars.insert(name.clone(), value);
+        }
 
-        Ok(output)
+        Ok(Self(builder.body(body)?.into()))
     }
 
     pub fn get_header_keys(&self) -> Vec<String> {
@@ -101,18 +205,29 @@ impl ResponseExt {
 impl FromStr for RequestProfile {
     type Err = anyhow::Error;
 
+    /// A bare url for a GET request, or a curl command
     fn from_str(s: &str) -> Result<Self> {
+        if s.trim_start().starts_with("curl ") {
+            return crate::parse_curl(s);
+        }
         let mut url = Url::parse(s)?;
-        let qs = url.query_pairs();
-        let mut params = serde_json::json!({});
-        for (k, v) in qs {
-            params[&*k] = v.parse()?;
+        // values are kept as strings, a repeated key becomes an array
+        let mut params = serde_json::Map::new();
+        for (k, v) in url.query_pairs() {
+            let v = serde_json::Value::String(v.into_owned());
+            match params.get_mut(&*k) {
+                Some(serde_json::Value::Array(values)) => values.push(v),
+                Some(first) => *first = serde_json::Value::Array(vec![first.take(), v]),
+                None => {
+                    params.insert(k.into_owned(), v);
+                }
+            }
         }
         url.set_query(None);
         Ok(Self::new(
             Method::GET,
             url,
-            Some(params),
+            Some(serde_json::Value::Object(params)),
             HeaderMap::new(),
             None,
         ))
@@ -130,19 +245,105 @@ impl RequestProfile {
         Self {
             method,
             url,
+            path_params: BTreeMap::new(),
             params,
+            query_format: QueryFormat::default(),
             headers,
             body,
+            body_file: None,
+            files: BTreeMap::new(),
+            graphql: None,
+            cookies: None,
+            matrix: Matrix::new(),
+            expect: None,
+        }
+    }
+
+    /// Substitute `{{name}}` variables in url, headers, params and body
+    pub fn render(&self, vars: &Variables) -> Result<Self> {
+        let mut profile = self.clone();
+        profile.url = render_url(&self.url, vars)?;
+        for v in profile.headers.values_mut() {
+            if let std::result::Result::Ok(s) = v.to_str() {
+                *v = render_str(s, vars)?.parse()?;
+            }
+        }
+        for v in profile.path_params.values_mut() {
+            *v = render_json(v, vars)?;
+        }
+        if let Some(params) = self.params.as_ref() {
+            profile.params = Some(render_json(params, vars)?);
+        }
+        if let Some(body) = self.body.as_ref() {
+            profile.body = Some(render_json(body, vars)?);
         }
+        if let Some(graphql) = profile.graphql.as_mut() {
+            if let Some(variables) = graphql.variables.as_ref() {
+                graphql.variables = Some(render_json(variables, vars)?);
+            }
+        }
+        Ok(profile)
+    }
+
+    /// The profile rendered once per data row and matrix combination,
+    /// or the profile itself without rows and matrix
+    pub fn expand(&self, rows: &[(String, Variables)]) -> Result<Vec<(String, Self)>> {
+        if self.matrix.is_empty() && rows.is_empty() {
+            return Ok(vec![(String::new(), self.clone())]);
+        }
+        matrix::runs(&self.matrix, rows)
+            .into_iter()
+            .map(|(title, vars)| {
+                let profile = self.render(&vars).with_context(|| title.clone())?;
+                Ok((title, profile))
+            })
+            .collect()
+    }
+
+    /// A fresh cookie jar seeded from the profile, if the profile enables cookies
+    pub fn cookie_jar(&self) -> Result<Option<Arc<CookieJar>>> {
+        if self.cookies.is_none() {
+            return Ok(None);
+        }
+        let jar = CookieJar::default();
+        self.seed_cookies(&jar)?;
+        Ok(Some(Arc::new(jar)))
+    }
+
+    pub fn seed_cookies(&self, jar: &CookieJar) -> Result<()> {
+        if let Some(cookies) = self.cookies.as_ref() {
+            if let Some(file) = cookies.file.as_ref() {
+                let content = std::fs::read_to_string(file)
+                    .map_err(|e| anyhow::anyhow!("read cookies file {} error: {}", file, e))?;
+                jar.load_netscape(&content)?;
+            }
+            for (k, v) in &cookies.values {
+                jar.add(k, v, &self.url);
+            }
+        }
+        Ok(())
     }
 
     pub async fn send(&self, args: &ExtraArgs) -> Result<ResponseExt> {
-        let (headers, query, body) = self.generate(args)?;
-        let client = reqwest::Client::new();
+        let jar = self.cookie_jar()?;
+        self.send_with(args, jar).await
+    }
+
+    /// Send with the given cookie jar, so cookies set by a response are kept for later requests
+    pub async fn send_with(
+        &self,
+        args: &ExtraArgs,
+        jar: Option<Arc<CookieJar>>,
+    ) -> Result<ResponseExt> {
+        let (method, url, headers, body) = self.prepare(args)?;
+        let mut builder = reqwest::Client::builder();
+        if let Some(jar) = jar {
+            builder = builder.cookie_provider(jar);
+        }
+        let client = builder.build()?;
         let req = client
-            .request(self.method.clone(), self.url.clone())
+            .request(method, url)
             .headers(headers)
-            .query(&query)
             .body(body)
             .build()?;
         let res = client.execute(req).await?;
@@ -150,20 +351,72 @@ impl RequestProfile {
         Ok(ResponseExt(res))
     }
 
+    /// The method, url, headers and body that are sent, without cookies
+    pub(crate) fn prepare(&self, args: &ExtraArgs) -> Result<(Method, Url, HeaderMap, Vec<u8>)> {
+        let (headers, query, body) = self.generate(args)?;
+        let method = match self.graphql {
+            Some(_) => Method::POST,
+            None => self.method.clone(),
+        };
+        Ok((method, self.generate_url(args, &query)?, headers, body))
+    }
+
     pub fn get_url(&self, args: &ExtraArgs) -> anyhow::Result<String> {
-        let (_, params, _) = self.generate(args)?;
-        let mut url = self.url.clone();
-        if !params.as_object().unwrap().is_empty() {
-            let query = serde_qs::to_string(&params)?;
+        let (_, query, _) = self.generate(args)?;
+        Ok(self.generate_url(args, &query)?.to_string())
+    }
+
+    /// The url as sent, with path params filled and the query encoded
+    fn generate_url(&self, args: &ExtraArgs, query: &serde_json::Value) -> Result<Url> {
+        let mut params: BTreeMap<String, String> = self
+            .path_params
+            .iter()
+            .map(|(k, v)| (k.clone(), value_to_string(v)))
+            .collect();
+        params.extend(args.paths.iter().cloned());
+        // without path params braces are part of the path, like an encoded `%7Bx%7D`
+        let mut url = match params.is_empty() {
+            true => self.url.clone(),
+            false => render_path(&self.url, &params)?,
+        };
+        let query = query::encode_query(query, self.query_format)?;
+        if !query.is_empty() {
+            let query = match url.query() {
+                Some(q) if !q.is_empty() => format!("{}&{}", q, query),
+                _ => query,
+            };
             url.set_query(Some(&query));
         }
-        Ok(url.to_string())
+        Ok(url)
+    }
+
+    /// The body with the overrides of `args` applied
+    pub(crate) fn body_with(&self, args: &ExtraArgs) -> Result<Option<serde_json::Value>> {
+        let mut body = self.body.clone();
+        if !args.bodys.is_empty() {
+            let body = body.get_or_insert_with(|| serde_json::json!({}));
+            if !body.is_object() && !body.is_array() {
+                return Err(anyhow::anyhow!("body overrides need a json body"));
+            }
+            for (k, v) in &args.bodys {
+                v.apply(k, body)?;
+            }
+        }
+        Ok(body)
+    }
+
+    /// The file parts with the ones of `args` added
+    pub(crate) fn files_with(&self, args: &ExtraArgs) -> Result<BTreeMap<String, FilePart>> {
+        let mut files = self.files.clone();
+        for (k, v) in &args.files {
+            files.insert(k.clone(), v.parse()?);
+        }
+        Ok(files)
     }
 
-    fn generate(&self, args: &ExtraArgs) -> Result<(HeaderMap, serde_json::Value, String)> {
+    fn generate(&self, args: &ExtraArgs) -> Result<(HeaderMap, serde_json::Value, Vec<u8>)> {
         let mut headers = self.headers.clone();
         let mut query = self.params.clone().unwrap_or_else(|| serde_json::json!({}));
-        let mut body = self.body.clone().unwrap_or_else(|| serde_json::json!({}));
 
         for (k, v) in &args.headers {
             headers.insert(HeaderName::from_str(k)?, v.parse()?);
@@ -172,35 +425,111 @@ impl RequestProfile {
         if !headers.contains_key(header::CONTENT_TYPE) {
             headers.insert(
                 header::CONTENT_TYPE,
-                HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
+                HeaderValue::from_static(self.default_content_type()),
             );
         }
 
         for (k, v) in &args.querys {
-            query[k] = v.parse()?;
+            v.apply_key(k, &mut query)?;
         }
 
-        for (k, v) in &args.bodys {
-            body[k] = v.parse()?;
+        if let Some(graphql) = self.graphql.as_ref() {
+            // overrides of the body go to the query variables
+            let body = serde_json::to_vec(&graphql.body(&args.bodys)?)?;
+            return Ok((headers, query, body));
         }
 
-        let ct = get_content_type(&headers);
-        match ct.unwrap().as_str() {
-            n if n == mime::APPLICATION_JSON => {
-                let body = serde_json::to_string(&body)?;
-                Ok((headers, query, body))
+        if let Some(file) = self.body_file.as_ref() {
+            if !args.bodys.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "body overrides can't change the body file {}",
+                    file
+                ));
+            }
+            let body = std::fs::read(file)
+                .map_err(|e| anyhow::anyhow!("read body file {} error: {}", file, e))?;
+            return Ok((headers, query, body));
+        }
+
+        let body = self.body_with(args)?;
+        let ct = get_content_type(&headers).unwrap();
+        if BodyKind::from(ct.as_str()) == BodyKind::Multipart {
+            let files = self.files_with(args)?;
+            let (boundary, body) = multipart::encode_multipart(body.as_ref(), &files)?;
+            headers.insert(
+                header::CONTENT_TYPE,
+                format!("{}; boundary={}", mime::MULTIPART_FORM_DATA, boundary).parse()?,
+            );
+            return Ok((headers, query, body));
+        }
+        if !args.files.is_empty() {
+            return Err(anyhow::anyhow!(
+                "file parts need a multipart/form-data body"
+            ));
+        }
+
+        let body = match (BodyKind::from(ct.as_str()), body) {
+            (BodyKind::Json, body) => {
+                serde_json::to_vec(&body.unwrap_or_else(|| serde_json::json!({})))?
             }
-            n if n == mime::APPLICATION_WWW_FORM_URLENCODED || n == mime::FORM_DATA => {
-                let body = serde_urlencoded::to_string(&body)?;
-                Ok((headers, query, body))
+            (BodyKind::Form, Some(serde_json::Value::String(text))) => text.into_bytes(),
+            (BodyKind::Form, body) => {
+                serde_urlencoded::to_string(body.unwrap_or_else(|| serde_json::json!({})))?
+                    .into_bytes()
             }
-            _ => Err(anyhow::anyhow!("unsupported")),
+            (BodyKind::NdJson, Some(serde_json::Value::Array(lines))) => {
+                let mut output = String::new();
+                for line in lines {
+                    writeln!(&mut output, "{}", serde_json::to_string(&line)?)?;
+                }
+                output.into_bytes()
+            }
+            (_, Some(serde_json::Value::String(text))) => text.into_bytes(),
+            (_, None) => vec![],
+            _ => return Err(anyhow::anyhow!("unsupported body for content-type {}", ct)),
+        };
+        Ok((headers, query, body))
+    }
+
+    fn default_content_type(&self) -> &'static str {
+        match self.body_file {
+            Some(_) => "application/octet-stream",
+            None if !self.files.is_empty() => "multipart/form-data",
+            None => "application/json",
+        }
+    }
+}
+
+/// How the body is encoded for a content-type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyKind {
+    /// any json value
+    Json,
+    /// a json object, url-encoded, or an encoded string sent as-is
+    Form,
+    /// a json object as text fields, plus file parts
+    Multipart,
+    /// a json array, one line per item, or a raw string
+    NdJson,
+    /// a raw string, sent as-is
+    Raw,
+}
+
+impl From<&str> for BodyKind {
+    fn from(ct: &str) -> Self {
+        match ct {
+            n if n == mime::APPLICATION_JSON || n.ends_with("+json") => BodyKind::Json,
+            n if n == mime::APPLICATION_WWW_FORM_URLENCODED => BodyKind::Form,
+            n if n == mime::MULTIPART_FORM_DATA => BodyKind::Multipart,
+            n if n.ends_with("ndjson") || n.ends_with("jsonl") => BodyKind::NdJson,
+            _ => BodyKind::Raw,
         }
     }
 }
 
 impl ValidateConfig for RequestProfile {
     fn validate(&self) -> Result<()> {
+        matrix::validate(&self.matrix)?;
         if let Some(params) = self.params.as_ref() {
             if !params.is_object() {
                 return Err(anyhow::anyhow!(
@@ -209,14 +538,52 @@ impl ValidateConfig for RequestProfile {
                 ));
             }
         }
+        for (k, v) in &self.path_params {
+            if v.is_object() || v.is_array() {
+                return Err(anyhow::anyhow!(
+                    "config path_params error\n {} must be a string or number",
+                    k
+                ));
+            }
+        }
+        if let Some(graphql) = self.graphql.as_ref() {
+            if self.body.is_some() || self.body_file.is_some() || !self.files.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "config graphql error\n graphql can't be used with body, body_file or files"
+                ));
+            }
+            graphql.validate()?;
+        }
+        let ct = get_content_type(&self.headers)
+            .unwrap_or_else(|| self.default_content_type().to_string());
+        if !self.files.is_empty() && BodyKind::from(ct.as_str()) != BodyKind::Multipart {
+            return Err(anyhow::anyhow!(
+                "config files error\n files need a multipart/form-data body"
+            ));
+        }
         if let Some(body) = self.body.as_ref() {
-            if !body.is_object() {
+            if self.body_file.is_some() {
+                return Err(anyhow::anyhow!(
+                    "config body error\n body and body_file are both set"
+                ));
+            }
+            let valid = match BodyKind::from(ct.as_str()) {
+                BodyKind::Json => true,
+                BodyKind::Form => body.is_object() || body.is_string(),
+                BodyKind::Multipart => body.is_object(),
+                BodyKind::NdJson => body.is_array() || body.is_string(),
+                BodyKind::Raw => body.is_string(),
+            };
+            if !valid {
                 return Err(anyhow::anyhow!(
                     "config body error\n {}",
                     serde_yaml::to_string(body)?
                 ));
             }
         }
+        if let Some(expect) = self.expect.as_ref() {
+            expect.validate()?;
+        }
         Ok(())
     }
 }
@@ -226,32 +593,103 @@ pub fn get_status_text(res: &Response) -> Result<String> {
 }
 
 pub fn get_header_text(res: &Response, skip: &[String]) -> anyhow::Result<String> {
+    let profile = ResponseProfile::new(skip.to_vec(), vec![]);
+    get_filtered_header_text(res, &profile)
+}
+
+/// The header text without skipped headers and with masked values
+pub fn get_filtered_header_text(res: &Response, profile: &ResponseProfile) -> Result<String> {
     let mut output = String::new();
     let headers = res.headers();
+    let mut cookies = vec![];
     for (k, v) in headers.iter() {
-        if !skip.iter().any(|sh| sh == k.as_str()) {
+        if profile.skip_headers.iter().any(|sh| sh == k.as_str()) {
+            continue;
+        }
+        let masks: Vec<_> = profile
+            .mask
+            .iter()
+            .filter(|m| m.is_header(k.as_str()))
+            .collect();
+        if masks.is_empty() && k != header::SET_COOKIE {
             writeln!(&mut output, "{}: {:?}", k, v)?;
+            continue;
+        }
+        let mut value = String::from_utf8_lossy(v.as_bytes()).into_owned();
+        for mask in masks {
+            value = mask.apply(&value)?;
+        }
+        if k == header::SET_COOKIE {
+            cookies.push(value);
+        } else {
+            writeln!(&mut output, "{}: {:?}", k, value)?;
         }
     }
+    cookies.sort();
+    for cookie in cookies {
+        write!(
+            &mut output,
+            "{}: {}",
+            header::SET_COOKIE,
+            get_set_cookie_text(&cookie)?
+        )?;
+    }
     Ok(output)
 }
 
 pub async fn get_body_text(res: Response, skip: &[String]) -> anyhow::Result<String> {
-    let mut output = String::new();
-    let headers = res.headers();
-    //output.push_str("\n");
-    let ct = get_content_type(&headers);
-    let text = res.text().await?;
-    match ct.unwrap().as_str() {
-        n if n == mime::APPLICATION_JSON => {
-            let text = filter_json(&text, skip)?;
-            output.push_str(&text);
+    let profile = ResponseProfile::new(vec![], skip.to_vec());
+    get_filtered_body_text(res, &profile).await
+}
+
+/// The body text normalized by the rules of a response profile
+pub async fn get_filtered_body_text(res: Response, profile: &ResponseProfile) -> Result<String> {
+    FilteredBody::read(res, profile).await?.text(profile)
+}
+
+/// A body normalized by a response profile, json is kept parsed until it's compared
+struct FilteredBody {
+    json: Option<serde_json::Value>,
+    text: String,
+    violations: Vec<String>,
+}
+
+impl FilteredBody {
+    async fn read(res: Response, profile: &ResponseProfile) -> Result<Self> {
+        let ct = get_content_type(res.headers());
+        let text = res.text().await?;
+        let violations = profile.schema_violations(&text)?;
+        let json = match ct.as_deref().map(BodyKind::from) {
+            Some(BodyKind::Json) => {
+                let mut json = serde_json::from_str(&text)?;
+                profile.filter_json(&mut json)?;
+                Some(json)
+            }
+            _ => None,
+        };
+        Ok(Self {
+            json,
+            text,
+            violations,
+        })
+    }
+
+    fn text(&self, profile: &ResponseProfile) -> Result<String> {
+        let mut output = match self.json.as_ref() {
+            Some(json) => serde_json::to_string_pretty(json)?,
+            None => self.text.clone(),
+        };
+        for mask in profile.mask.iter().filter(|m| m.is_body()) {
+            output = mask.apply(&output)?;
         }
-        _ => {
-            output.push_str(&text);
+        if !self.violations.is_empty() {
+            writeln!(&mut output, "\n\nschema violations:")?;
+            for violation in &self.violations {
+                writeln!(&mut output, "  {}", violation)?;
+            }
         }
+        Ok(output)
     }
-    Ok(output)
 }
 
 fn get_content_type(headers: &HeaderMap) -> Option<String> {
@@ -260,16 +698,6 @@ fn get_content_type(headers: &HeaderMap) -> Option<String> {
         .and_then(|v| v.to_str().unwrap().split(";").next().map(|v| v.to_string()))
 }
 
-fn filter_json(text: &str, skip: &[String]) -> Result<String> {
-    let mut json = serde_json::from_str(&text)?;
-    if let serde_json::Value::Object(ref mut obj) = json {
-        for k in skip {
-            obj.remove(k);
-        }
-    }
-    Ok(serde_json::to_string_pretty(&json)?)
-}
-
 fn empty_json_value(v: &Option<serde_json::Value>) -> bool {
     v.as_ref().map_or(true, |v| {
         v.is_null() || (v.is_object() && v.as_object().unwrap().is_empty())
@@ -319,6 +747,158 @@ mod tests {
         assert_eq!(res.status(), StatusCode::OK);
     }
 
+    #[test]
+    fn t4() {
+        let url = Url::parse("https://example.com/upload").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        let profile =
+            RequestProfile::new(Method::POST, url.clone(), None, headers, Some(json!("hi")));
+        profile.validate().unwrap();
+        let (_, _, body) = profile.generate(&Default::default()).unwrap();
+        assert_eq!(body, b"hi");
+
+        let profile = RequestProfile::new(
+            Method::POST,
+            url.clone(),
+            None,
+            HeaderMap::new(),
+            Some(json!([1, "a"])),
+        );
+        profile.validate().unwrap();
+        let (_, _, body) = profile.generate(&Default::default()).unwrap();
+        assert_eq!(body, br#"[1,"a"]"#);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson"),
+        );
+        let profile = RequestProfile::new(
+            Method::POST,
+            url.clone(),
+            None,
+            headers,
+            Some(json!([{"a": 1}, {"b": 2}])),
+        );
+        let (_, _, body) = profile.generate(&Default::default()).unwrap();
+        assert_eq!(body, b"{\"a\":1}\n{\"b\":2}\n");
+
+        let mut profile = RequestProfile::new(Method::POST, url, None, HeaderMap::new(), None);
+        profile.body_file = Some("fixtures/req.yml".to_string());
+        let (headers, _, body) = profile.generate(&Default::default()).unwrap();
+        assert_eq!(headers[header::CONTENT_TYPE], "application/octet-stream");
+        assert_eq!(body, std::fs::read("fixtures/req.yml").unwrap());
+        let args: ExtraArgs = vec![crate::cli::parse_key_val("@title=x").unwrap()].into();
+        assert!(profile.generate(&args).is_err());
+    }
+
+    #[test]
+    fn t5() {
+        let url = Url::parse("https://example.com/upload").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/xml"),
+        );
+        let profile = RequestProfile::new(Method::POST, url, None, headers, Some(json!({"a": 1})));
+        assert!(profile.validate().is_err());
+    }
+
+    #[test]
+    fn t6() {
+        let url = Url::parse("https://example.com/graphql").unwrap();
+        let mut profile = RequestProfile::new(Method::GET, url, None, HeaderMap::new(), None);
+        profile.graphql = Some(GraphqlRequest {
+            query: Some("{ todo { id } }".to_string()),
+            ..Default::default()
+        });
+        profile.validate().unwrap();
+        let (headers, _, body) = profile.generate(&Default::default()).unwrap();
+        assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+        assert_eq!(body, br#"{"query":"{ todo { id } }"}"#);
+
+        let response = ResponseProfile::new(vec![], vec!["data.todo.updatedAt".to_string()]);
+        let text = filter_json(
+            r#"{"data": {"todo": {"id": 1, "updatedAt": "now"}}}"#,
+            &response,
+        )
+        .unwrap();
+        assert_eq!(
+            text,
+            serde_json::to_string_pretty(&json!({"data": {"todo": {"id": 1}}})).unwrap()
+        );
+    }
+
+    #[test]
+    fn t7() {
+        let res: Response = http::Response::builder()
+            .header(
+                header::SET_COOKIE,
+                HeaderValue::from_bytes(b"name=caf\xc3\xa9").unwrap(),
+            )
+            .header(
+                header::SET_COOKIE,
+                HeaderValue::from_bytes(b"id=\xe9; Path=/").unwrap(),
+            )
+            .body("")
+            .unwrap()
+            .into();
+        let text = get_header_text(&res, &[]).unwrap();
+        assert_eq!(
+            text,
+            "set-cookie: \"id=\u{fffd}\"\n  path: \"/\"\nset-cookie: \"name=café\"\n"
+        );
+    }
+
+    #[test]
+    fn t8() {
+        let url = Url::parse("https://example.com/files/%7Bx%7D/{id}").unwrap();
+        let profile = RequestProfile::new(Method::GET, url, None, HeaderMap::new(), None);
+        assert_eq!(
+            profile.get_url(&Default::default()).unwrap(),
+            "https://example.com/files/%7Bx%7D/%7Bid%7D"
+        );
+        let args: ExtraArgs = vec![crate::cli::parse_key_val(":id=1").unwrap()].into();
+        assert!(profile.get_url(&args).is_err());
+        let args: ExtraArgs = vec![
+            crate::cli::parse_key_val(":id=1").unwrap(),
+            crate::cli::parse_key_val(":x=a b").unwrap(),
+        ]
+        .into();
+        assert_eq!(
+            profile.get_url(&args).unwrap(),
+            "https://example.com/files/a%20b/1"
+        );
+        let args: ExtraArgs = vec![crate::cli::parse_key_val("page.size=10").unwrap()].into();
+        assert_eq!(
+            profile.get_url(&args).unwrap(),
+            "https://example.com/files/%7Bx%7D/%7Bid%7D?page.size=10"
+        );
+    }
+
+    #[test]
+    fn t9() {
+        let skip = ["a.b", "x[0", "c.d"].map(String::from).to_vec();
+        let response = ResponseProfile::new(vec![], skip);
+        response.validate().unwrap();
+        let text = filter_json(
+            r#"{"a.b": 1, "a": {"b": 2}, "x[0": 3, "c": {"d": 4, "e": 5}}"#,
+            &response,
+        )
+        .unwrap();
+        assert_eq!(
+            text,
+            serde_json::to_string_pretty(&json!({"a": {"b": 2}, "c": {"e": 5}})).unwrap()
+        );
+    }
+
+    fn filter_json(text: &str, profile: &ResponseProfile) -> Result<String> {
+        let mut json = serde_json::from_str(text)?;
+        profile.filter_json(&mut json)?;
+        Ok(serde_json::to_string_pretty(&json)?)
+    }
+
     fn mock_server(path: &str, body: &serde_json::Value) {
         let _m = mock("GET", path)
             .with_status(200)
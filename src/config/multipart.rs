@@ -0,0 +1,175 @@
+use crate::template::value_to_string;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A file part of a multipart/form-data body
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FilePart {
+    pub path: String,
+    /// defaults to the file name of `path`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub filename: Option<String>,
+    /// defaults to application/octet-stream
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub content_type: Option<String>,
+}
+
+impl FilePart {
+    fn filename(&self) -> String {
+        self.filename.clone().unwrap_or_else(|| {
+            Path::new(&self.path)
+                .file_name()
+                .map(|v| v.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.path.clone())
+        })
+    }
+
+    /// The curl style part, like `./avatar.png;type=image/png`
+    pub fn spec(&self) -> String {
+        let mut spec = self.path.clone();
+        if let Some(ct) = self.content_type.as_ref() {
+            spec.push_str(&format!(";type={}", ct));
+        }
+        if let Some(filename) = self.filename.as_ref() {
+            spec.push_str(&format!(";filename={}", filename));
+        }
+        spec
+    }
+}
+
+/// Parse a curl style part like `./avatar.png;type=image/png;filename=me.png`
+impl FromStr for FilePart {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split(';');
+        let path = parts.next().unwrap_or_default().trim();
+        if path.is_empty() {
+            return Err(anyhow!("invalid file part: {}", s));
+        }
+        let mut part = FilePart {
+            path: path.to_string(),
+            filename: None,
+            content_type: None,
+        };
+        for attr in parts {
+            match attr.trim().split_once('=') {
+                Some(("type", v)) => part.content_type = Some(v.to_string()),
+                Some(("filename", v)) => part.filename = Some(v.to_string()),
+                _ => return Err(anyhow!("invalid file part attribute: {}", attr)),
+            }
+        }
+        Ok(part)
+    }
+}
+
+/// Encode text fields from a json object and file parts, returning the boundary and the body
+pub fn encode_multipart(
+    fields: Option<&serde_json::Value>,
+    files: &BTreeMap<String, FilePart>,
+) -> Result<(String, Vec<u8>)> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+    let boundary = format!("xdiff-{:032x}", nanos);
+    let mut body = vec![];
+
+    if let Some(fields) = fields {
+        let fields = fields
+            .as_object()
+            .ok_or_else(|| anyhow!("multipart fields need an object body"))?;
+        for (k, v) in fields {
+            write!(
+                body,
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary,
+                escape(k),
+                value_to_string(v)
+            )?;
+        }
+    }
+
+    for (k, part) in files {
+        let content_type = part
+            .content_type
+            .as_deref()
+            .unwrap_or("application/octet-stream");
+        if content_type.contains(['\r', '\n']) {
+            return Err(anyhow!("invalid content type of file part {}", k));
+        }
+        let content = std::fs::read(&part.path)
+            .map_err(|e| anyhow!("read file part {} error: {}", part.path, e))?;
+        write!(
+            body,
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+            boundary,
+            escape(k),
+            escape(&part.filename()),
+            content_type
+        )?;
+        body.extend_from_slice(&content);
+        body.extend_from_slice(b"\r\n");
+    }
+    write!(body, "--{}--\r\n", boundary)?;
+
+    Ok((boundary, body))
+}
+
+/// Quote, CR and LF in a name are percent-encoded like browsers do, so the part headers stay intact
+fn escape(name: &str) -> String {
+    name.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let part: FilePart = "fixtures/diff_test_txt1.txt;type=text/plain"
+            .parse()
+            .unwrap();
+        assert_eq!(part.filename(), "diff_test_txt1.txt");
+        let files = vec![("doc".to_string(), part)].into_iter().collect();
+        let (boundary, body) = encode_multipart(Some(&json!({"title": "hi"})), &files).unwrap();
+        let body = String::from_utf8(body).unwrap();
+        let content = std::fs::read_to_string("fixtures/diff_test_txt1.txt").unwrap();
+        let expected = format!(
+            "--{0}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhi\r\n\
+             --{0}\r\nContent-Disposition: form-data; name=\"doc\"; filename=\"diff_test_txt1.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n{1}\r\n--{0}--\r\n",
+            boundary, content
+        );
+        assert_eq!(body, expected);
+        assert!("a.png;size=1".parse::<FilePart>().is_err());
+    }
+
+    #[test]
+    fn t2() {
+        let part = FilePart {
+            path: "fixtures/diff_test_txt1.txt".to_string(),
+            filename: Some("a\"b\r\n.txt".to_string()),
+            content_type: None,
+        };
+        let files = vec![("f\"\n".to_string(), part.clone())]
+            .into_iter()
+            .collect();
+        let (_, body) = encode_multipart(Some(&json!({"x\"\r\ny": 1})), &files).unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.contains("name=\"x%22%0D%0Ay\"\r\n\r\n1\r\n"));
+        assert!(body.contains("name=\"f%22%0A\"; filename=\"a%22b%0D%0A.txt\"\r\n"));
+        let part = FilePart {
+            content_type: Some("text/plain\r\nX-Evil: 1".to_string()),
+            ..part
+        };
+        let files = vec![("f".to_string(), part)].into_iter().collect();
+        assert!(encode_multipart(None, &files).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::template::value_to_string;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::form_urlencoded;
+
+/// How arrays in params are written to the query string, objects are always `a[b]=c`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryFormat {
+    /// `a=1&a=2`
+    #[default]
+    Repeat,
+    /// `a[]=1&a[]=2`
+    Brackets,
+    /// `a=1,2`
+    Comma,
+}
+
+/// Encode a json object of params into a query string
+pub fn encode_query(params: &Value, format: QueryFormat) -> Result<String> {
+    let obj = params
+        .as_object()
+        .ok_or_else(|| anyhow!("params must be an object"))?;
+    let mut pairs = vec![];
+    for (k, v) in obj {
+        collect_pairs(k.clone(), v, format, &mut pairs);
+    }
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    for (k, v) in pairs {
+        serializer.append_pair(&k, &v);
+    }
+    Ok(serializer.finish())
+}
+
+fn collect_pairs(
+    key: String,
+    value: &Value,
+    format: QueryFormat,
+    pairs: &mut Vec<(String, String)>,
+) {
+    match value {
+        Value::Object(obj) => {
+            for (k, v) in obj {
+                collect_pairs(format!("{}[{}]", key, k), v, format, pairs);
+            }
+        }
+        Value::Array(arr) if arr.iter().any(|v| v.is_object() || v.is_array()) => {
+            for (i, v) in arr.iter().enumerate() {
+                collect_pairs(format!("{}[{}]", key, i), v, format, pairs);
+            }
+        }
+        Value::Array(arr) => match format {
+            QueryFormat::Repeat => {
+                pairs.extend(arr.iter().map(|v| (key.clone(), scalar_to_string(v))))
+            }
+            QueryFormat::Brackets => {
+                let key = format!("{}[]", key);
+                pairs.extend(arr.iter().map(|v| (key.clone(), scalar_to_string(v))))
+            }
+            QueryFormat::Comma => {
+                let v: Vec<_> = arr.iter().map(scalar_to_string).collect();
+                pairs.push((key, v.join(",")));
+            }
+        },
+        v => pairs.push((key, scalar_to_string(v))),
+    }
+}
+
+fn scalar_to_string(v: &Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        v => value_to_string(v),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let params = json!({"a": [1, 2], "f": {"b": "c d", "e": [true]}, "n": null});
+        assert_eq!(
+            encode_query(&params, QueryFormat::Repeat).unwrap(),
+            "a=1&a=2&f%5Bb%5D=c+d&f%5Be%5D=true&n="
+        );
+        assert_eq!(
+            encode_query(&params, QueryFormat::Brackets).unwrap(),
+            "a%5B%5D=1&a%5B%5D=2&f%5Bb%5D=c+d&f%5Be%5D%5B%5D=true&n="
+        );
+        assert_eq!(
+            encode_query(&json!({"a": [1, 2], "o": [{"x": 1}]}), QueryFormat::Comma).unwrap(),
+            "a=1%2C2&o%5B0%5D%5Bx%5D=1"
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use super::{is_default, RequestProfile, ResponseExt, ResponseProfile, ValidateConfig};
+use crate::{CookieJar, ExtraArgs, JsonPath, Side, Variables};
+use anyhow::{Context, Ok, Result};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Write as _, io::Write as _, sync::Arc};
+use url::Url;
+
+/// Ordered requests, later steps can use values captured from earlier responses
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Scenario {
+    pub steps: Vec<ScenarioStep>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScenarioStep {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub request: RequestProfile,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub capture: BTreeMap<String, Capture>,
+}
+
+/// Where a variable is captured from in a response
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Capture {
+    /// json path into the body
+    Json { json: String },
+    /// header value
+    Header { header: String },
+    /// regex on the body text, the first group if any
+    Regex { regex: String },
+}
+
+/// A scenario run against two targets, each step's responses are compared
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiffScenario {
+    /// scheme, host and port of each step url are replaced by the target,
+    /// a target path like `/api` is prepended to the step path
+    pub target1: Url,
+    pub target2: Url,
+    #[serde(flatten)]
+    pub scenario: Scenario,
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub response: ResponseProfile,
+}
+
+impl ScenarioStep {
+    pub fn title(&self, idx: usize) -> String {
+        match &self.name {
+            Some(name) => format!("step {} [{}]", idx + 1, name),
+            None => format!("step {}", idx + 1),
+        }
+    }
+
+    /// Render the request with `vars`, send it and capture values from the response into `vars`
+    pub async fn run(
+        &self,
+        args: &ExtraArgs,
+        vars: &mut Variables,
+        jar: Option<Arc<CookieJar>>,
+    ) -> Result<(RequestProfile, ResponseExt)> {
+        let req = self.request.render(vars)?;
+        let res = req.send_with(args, jar).await?;
+        let res = res.capture(&self.capture, vars).await?;
+        Ok((req, res))
+    }
+}
+
+impl Capture {
+    pub fn extract(&self, headers: &HeaderMap, body: &[u8]) -> Result<Option<serde_json::Value>> {
+        Ok(match self {
+            Capture::Json { json } => {
+                let body: serde_json::Value = serde_json::from_slice(body)?;
+                json.parse::<JsonPath>()?.get(&body).cloned()
+            }
+            Capture::Header { header } => headers
+                .get(header)
+                .map(|v| v.to_str().map(|v| v.into()))
+                .transpose()?,
+            Capture::Regex { regex } => {
+                let text = String::from_utf8_lossy(body);
+                regex::Regex::new(regex)?.captures(&text).map(|caps| {
+                    caps.get(1)
+                        .or_else(|| caps.get(0))
+                        .map_or("", |m| m.as_str())
+                        .into()
+                })
+            }
+        })
+    }
+}
+
+impl Scenario {
+    /// Run the steps in order, returning each rendered request with its response
+    pub async fn run(
+        &self,
+        args: &ExtraArgs,
+        vars: &mut Variables,
+        jar: Option<Arc<CookieJar>>,
+    ) -> Result<Vec<(RequestProfile, ResponseExt)>> {
+        let jar = match jar {
+            Some(jar) => Some(jar),
+            None if self.steps.iter().any(|s| s.request.cookies.is_some()) => {
+                Some(Arc::new(CookieJar::default()))
+            }
+            None => None,
+        };
+        if let Some(jar) = jar.as_ref() {
+            for step in &self.steps {
+                step.request.seed_cookies(jar)?;
+            }
+        }
+
+        let mut output = vec![];
+        for (idx, step) in self.steps.iter().enumerate() {
+            let res = step
+                .run(args, vars, jar.clone())
+                .await
+                .with_context(|| step.title(idx))?;
+            output.push(res);
+        }
+        Ok(output)
+    }
+
+    pub fn rebase(&self, target: &Url) -> Result<Self> {
+        let mut scenario = self.clone();
+        for step in scenario.steps.iter_mut() {
+            let url = &mut step.request.url;
+            url.set_scheme(target.scheme())
+                .map_err(|_| anyhow::anyhow!("invalid target {}", target))?;
+            url.set_host(target.host_str())?;
+            url.set_port(target.port())
+                .map_err(|_| anyhow::anyhow!("invalid target {}", target))?;
+            let base = target.path().trim_end_matches('/');
+            if !base.is_empty() {
+                let path = format!("{}{}", base, url.path());
+                url.set_path(&path);
+            }
+        }
+        Ok(scenario)
+    }
+}
+
+impl DiffScenario {
+    pub async fn diff(&self, args: ExtraArgs) -> Result<String> {
+        self.diff_with(args, None).await
+    }
+
+    /// Diff with a cookie jar shared by both targets instead of one jar per target
+    pub async fn diff_with(&self, args: ExtraArgs, jar: Option<Arc<CookieJar>>) -> Result<String> {
+        let res1 = self
+            .scenario
+            .rebase(&self.target1)?
+            .run(
+                &args.for_side(Side::Req1),
+                &mut Variables::new(),
+                jar.clone(),
+            )
+            .await
+            .context("target1 error")?;
+        let res2 = self
+            .scenario
+            .rebase(&self.target2)?
+            .run(&args.for_side(Side::Req2), &mut Variables::new(), jar)
+            .await
+            .context("target2 error")?;
+
+        let mut output = String::new();
+        for (idx, ((_, r1), (_, r2))) in res1.into_iter().zip(res2).enumerate() {
+            writeln!(&mut output, "{}", self.scenario.steps[idx].title(idx))?;
+            write!(&mut output, "{}", r1.diff(r2, &self.response).await?)?;
+        }
+
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        write!(stdout, "{}", output)?;
+
+        Ok("".to_string())
+    }
+}
+
+impl ValidateConfig for Scenario {
+    fn validate(&self) -> Result<()> {
+        if self.steps.is_empty() {
+            return Err(anyhow::anyhow!("scenario has no steps"));
+        }
+        for (idx, step) in self.steps.iter().enumerate() {
+            step.validate().with_context(|| step.title(idx))?;
+        }
+        Ok(())
+    }
+}
+
+impl ValidateConfig for ScenarioStep {
+    fn validate(&self) -> Result<()> {
+        self.request.validate()?;
+        if !self.request.matrix.is_empty() {
+            return Err(anyhow::anyhow!("matrix is not supported in scenario steps"));
+        }
+        for (name, capture) in &self.capture {
+            capture
+                .validate()
+                .with_context(|| format!("capture {}", name))?;
+        }
+        Ok(())
+    }
+}
+
+impl ValidateConfig for DiffScenario {
+    fn validate(&self) -> Result<()> {
+        self.scenario.validate()?;
+        self.response.validate().context("response error")
+    }
+}
+
+impl ValidateConfig for Capture {
+    fn validate(&self) -> Result<()> {
+        match self {
+            Capture::Json { json } => {
+                json.parse::<JsonPath>()?;
+            }
+            Capture::Header { .. } => {}
+            Capture::Regex { regex } => {
+                regex::Regex::new(regex)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::mock;
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn t1() {
+        let _m1 = mock("POST", "/scenario/todos")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_header("x-trace", "abc")
+            .with_body(r#"{"id": 7}"#)
+            .create();
+        let _m2 = mock("GET", "/scenario/todos/7").with_status(200).create();
+
+        let yaml = r#"
+steps:
+  - name: create
+    url: SERVER/scenario/todos
+    method: POST
+    capture:
+      id:
+        json: $.id
+      trace:
+        header: x-trace
+  - url: SERVER/scenario/todos/{{id}}
+"#
+        .replace("SERVER", &mockito::server_url());
+        let scenario: Scenario = serde_yaml::from_str(&yaml).unwrap();
+        scenario.validate().unwrap();
+
+        let mut vars = Variables::new();
+        let res = scenario
+            .run(&Default::default(), &mut vars, None)
+            .await
+            .unwrap();
+        assert_eq!(vars["id"], json!(7));
+        assert_eq!(vars["trace"], json!("abc"));
+        let (req, res) = res.into_iter().nth(1).unwrap();
+        assert_eq!(req.url.path(), "/scenario/todos/7");
+        assert_eq!(res.into_inner().status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn t2() {
+        let scenario: Scenario =
+            serde_yaml::from_str("steps:\n  - url: http://localhost:8080/todos/1?a=1\n").unwrap();
+        let target = Url::parse("https://example.com/api/").unwrap();
+        let rebased = scenario.rebase(&target).unwrap();
+        assert_eq!(
+            rebased.steps[0].request.url.as_str(),
+            "https://example.com/api/todos/1?a=1"
+        );
+        let target = Url::parse("https://example.com").unwrap();
+        let rebased = scenario.rebase(&target).unwrap();
+        assert_eq!(
+            rebased.steps[0].request.url.as_str(),
+            "https://example.com/todos/1?a=1"
+        );
+    }
+}
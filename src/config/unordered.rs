@@ -0,0 +1,141 @@
+use super::ValidateConfig;
+use crate::JsonPath;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// Arrays compared regardless of the order of their items, like search results.
+/// Items are sorted by `sort_by`, or by their json text, ties are broken by the json text
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Unordered {
+    /// json path of the arrays like `data.items` or `results[*].tags`
+    pub path: String,
+    /// key or json path of the items to sort by, like `id` or `meta.rank`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sort_by: Option<String>,
+    /// compare as sets, duplicate items are dropped
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub unique: bool,
+}
+
+impl Unordered {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn sort(&self, json: &mut Value) -> Result<()> {
+        let key = self
+            .sort_by
+            .as_ref()
+            .map(|k| k.parse::<JsonPath>())
+            .transpose()?;
+        for value in self.path.parse::<JsonPath>()?.select_mut(json) {
+            let items = match value {
+                Value::Array(items) => items,
+                _ => continue,
+            };
+            let mut keyed: Vec<_> = items
+                .drain(..)
+                .map(|item| (item.to_string(), item))
+                .collect();
+            keyed.sort_by(|(a_text, a), (b_text, b)| {
+                let by_key = match key.as_ref() {
+                    Some(key) => match (key.get(a), key.get(b)) {
+                        (Some(a), Some(b)) => compare(a, b),
+                        (a, b) => a.is_some().cmp(&b.is_some()),
+                    },
+                    None => Ordering::Equal,
+                };
+                by_key.then_with(|| a_text.cmp(b_text))
+            });
+            if self.unique {
+                keyed.dedup_by(|(a, _), (b, _)| a == b);
+            }
+            items.extend(keyed.into_iter().map(|(_, item)| item));
+        }
+        Ok(())
+    }
+
+    /// Arrays inside other arrays are sorted before the outer ones
+    pub fn depth(&self) -> usize {
+        self.path
+            .parse::<JsonPath>()
+            .map(|p| p.segments().len())
+            .unwrap_or_default()
+    }
+}
+
+/// Numbers by value, strings alphabetically, other values by their json text
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (a, b) => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+impl ValidateConfig for Unordered {
+    fn validate(&self) -> Result<()> {
+        self.path.parse::<JsonPath>()?;
+        if let Some(key) = self.sort_by.as_ref() {
+            key.parse::<JsonPath>()
+                .with_context(|| format!("sort_by error [{}]", key))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let mut body = json!({
+            "items": [
+                {"id": 10, "tags": ["b", "a", "b"]},
+                {"id": 9, "tags": ["c"]},
+                {"tags": []}
+            ]
+        });
+        let by_id = Unordered {
+            sort_by: Some("id".to_string()),
+            ..Unordered::new("items")
+        };
+        let tags = Unordered {
+            unique: true,
+            ..Unordered::new("items[*].tags")
+        };
+        assert!(tags.depth() > by_id.depth());
+        tags.sort(&mut body).unwrap();
+        by_id.sort(&mut body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "items": [
+                    {"tags": []},
+                    {"id": 9, "tags": ["c"]},
+                    {"id": 10, "tags": ["a", "b"]}
+                ]
+            })
+        );
+
+        let mut a = json!([{"x": 2}, {"x": 1}, {"x": 2}]);
+        let mut b = json!([{"x": 2}, {"x": 2}, {"x": 1}]);
+        let multiset = Unordered::new("$");
+        multiset.sort(&mut a).unwrap();
+        multiset.sort(&mut b).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a, json!([{"x": 1}, {"x": 2}, {"x": 2}]));
+        assert!(Unordered::new("items[").validate().is_err());
+    }
+}
@@ -1,11 +1,20 @@
-use super::{is_default, LoadConfig, RequestProfile, ValidateConfig};
-use crate::{utils::diff_text, ExtraArgs};
+use super::{
+    hook::run_hooks, is_default, matrix, Compare, DiffScenario, GraphqlResponse, Hook, LoadConfig,
+    Mask, Matrix, RequestProfile, Unordered, ValidateConfig,
+};
+use crate::{CookieJar, ExtraArgs, Jq, JsonPath, Schema, Side, Variables};
 use anyhow::{Context, Ok};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, io::Write};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    sync::Arc,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DiffConfig {
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub scenarios: HashMap<String, DiffScenario>,
     #[serde(flatten)]
     pub profiles: HashMap<String, DiffProfile>,
 }
@@ -16,6 +25,15 @@ pub struct DiffProfile {
     pub req2: RequestProfile,
     #[serde(skip_serializing_if = "is_default", default)]
     pub response: ResponseProfile,
+    /// run before the requests, captured values can be used in req1 and req2
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub setup: Vec<Hook>,
+    /// run after the diff, even if the setup or the diff failed
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub teardown: Vec<Hook>,
+    /// diff once per combination, values are used as `{{name}}` in req1 and req2
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub matrix: Matrix,
 }
 
 impl LoadConfig for DiffConfig {}
@@ -27,6 +45,11 @@ impl ValidateConfig for DiffConfig {
                 .validate()
                 .context(format!("profile error [{}]", name.to_string()))?;
         }
+        for (name, scenario) in &self.scenarios {
+            scenario
+                .validate()
+                .with_context(|| format!("scenario error [{}]", name))?;
+        }
         Ok(())
     }
 }
@@ -36,24 +59,133 @@ impl DiffConfig {
         self.profiles.get(name)
     }
 
+    pub fn get_scenario(&self, name: &str) -> Option<&DiffScenario> {
+        self.scenarios.get(name)
+    }
+
     pub fn new(profiles: HashMap<String, DiffProfile>) -> Self {
-        Self { profiles }
+        Self {
+            scenarios: HashMap::new(),
+            profiles,
+        }
     }
 }
 
 impl DiffProfile {
     pub async fn diff(&self, args: ExtraArgs) -> anyhow::Result<String> {
-        let r1 = self.req1.send(&args).await?;
-        let r2 = self.req2.send(&args).await?;
-
-        let t1 = r1.filter_text(&self.response).await?;
-        let t2 = r2.filter_text(&self.response).await?;
+        self.diff_with(args, None).await
+    }
 
-        let output = diff_text(t1.as_str(), t2.as_str())?;
+    /// Diff with a cookie jar shared by both requests instead of one jar per side
+    pub async fn diff_with(
+        &self,
+        args: ExtraArgs,
+        jar: Option<Arc<CookieJar>>,
+    ) -> anyhow::Result<String> {
+        self.diff_data(args, &[], jar).await
+    }
 
+    /// Diff once per data row and matrix combination, each run is reported as pass, diff or error
+    pub async fn diff_data(
+        &self,
+        args: ExtraArgs,
+        rows: &[(String, Variables)],
+        jar: Option<Arc<CookieJar>>,
+    ) -> anyhow::Result<String> {
+        let runs = matrix::runs(&self.matrix, rows);
         let stdout = std::io::stdout();
-        let mut stdout = stdout.lock();
-        write!(stdout, "{}", output)?;
+        if let [(title, vars)] = &runs[..] {
+            if title.is_empty() {
+                let output = self.diff_once(&args, vars.clone(), jar).await?;
+                write!(stdout.lock(), "{}", output)?;
+                return Ok("".to_string());
+            }
+        }
+
+        let (mut pass, mut diff, mut error) = (0, 0, 0);
+        for (title, vars) in runs {
+            let result = self.diff_once(&args, vars, jar.clone()).await;
+            let mut stdout = stdout.lock();
+            match result {
+                std::result::Result::Ok(output) if output.is_empty() => {
+                    pass += 1;
+                    writeln!(stdout, "[{}] pass", title)?;
+                }
+                std::result::Result::Ok(output) => {
+                    diff += 1;
+                    writeln!(stdout, "[{}] diff", title)?;
+                    write!(stdout, "{}", output)?;
+                }
+                Err(e) => {
+                    error += 1;
+                    writeln!(stdout, "[{}] error: {:#}", title, e)?;
+                }
+            }
+        }
+        writeln!(
+            stdout.lock(),
+            "{} runs: {} pass, {} diff, {} error",
+            pass + diff + error,
+            pass,
+            diff,
+            error
+        )?;
+        if error > 0 {
+            return Err(anyhow::anyhow!("{} runs failed", error));
+        }
+        Ok("".to_string())
+    }
+
+    async fn diff_once(
+        &self,
+        args: &ExtraArgs,
+        mut vars: Variables,
+        jar: Option<Arc<CookieJar>>,
+    ) -> anyhow::Result<String> {
+        // with hooks, they and both requests of a run share cookies, like a login session
+        let jar = match self.setup.is_empty() && self.teardown.is_empty() {
+            true => jar,
+            false => Some(jar.unwrap_or_default()),
+        };
+        let result = async {
+            run_hooks(&self.setup, &mut vars, jar.clone())
+                .await
+                .context("setup error")?;
+            self.diff_requests(args, &vars, jar.clone()).await
+        }
+        .await;
+        let teardown = run_hooks(&self.teardown, &mut vars, jar)
+            .await
+            .context("teardown error");
+        if let Err(te) = teardown {
+            return Err(match result {
+                Err(e) => anyhow::anyhow!("{:#}\n{:#}", e, te),
+                _ => te,
+            });
+        }
+        result
+    }
+
+    async fn diff_requests(
+        &self,
+        args: &ExtraArgs,
+        vars: &Variables,
+        jar: Option<Arc<CookieJar>>,
+    ) -> anyhow::Result<String> {
+        let req1 = self.req1.render(vars).context("req1 error")?;
+        let req2 = self.req2.render(vars).context("req2 error")?;
+        let (jar1, jar2) = match jar {
+            Some(jar) => {
+                req1.seed_cookies(&jar)?;
+                req2.seed_cookies(&jar)?;
+                (Some(jar.clone()), Some(jar))
+            }
+            None => (req1.cookie_jar()?, req2.cookie_jar()?),
+        };
+        let r1 = req1.send_with(&args.for_side(Side::Req1), jar1).await?;
+        let r2 = req2.send_with(&args.for_side(Side::Req2), jar2).await?;
+
+        let output = r1.diff(r2, &self.response).await?;
 
         //println!("{}", t1);
         //println!("{}", t2);
@@ -61,7 +193,7 @@ impl DiffProfile {
         //println!("{:?}", args);
         //println!("{:?}", &self.response);
 
-        Ok("".to_string())
+        Ok(output)
     }
 
     pub fn new(req1: RequestProfile, req2: RequestProfile, res: ResponseProfile) -> Self {
@@ -69,6 +201,9 @@ impl DiffProfile {
             req1,
             req2,
             response: res,
+            setup: vec![],
+            teardown: vec![],
+            matrix: Matrix::new(),
         }
     }
 }
@@ -77,16 +212,47 @@ impl ValidateConfig for DiffProfile {
     fn validate(&self) -> anyhow::Result<()> {
         _ = &self.req1.validate().context("req1 error")?;
         _ = &self.req2.validate().context("req2 error")?;
+        self.response.validate().context("response error")?;
+        if !self.req1.matrix.is_empty() || !self.req2.matrix.is_empty() {
+            return Err(anyhow::anyhow!(
+                "matrix goes on the diff profile, not on req1 or req2"
+            ));
+        }
+        matrix::validate(&self.matrix).context("matrix error")?;
+        for (idx, hook) in self.setup.iter().chain(&self.teardown).enumerate() {
+            hook.validate().with_context(|| hook.title(idx))?;
+        }
         Ok(())
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct ResponseProfile {
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub skip_headers: Vec<String>,
+    /// top level keys or json paths like `data.todo.updatedAt`, a top level key
+    /// like `a.b` is skipped as is when the body has it
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub skip_body: Vec<String>,
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub graphql: GraphqlResponse,
+    /// JSON Schema the body is checked against, like `schema.json` or
+    /// `openapi.yml#/components/schemas/Todo`, violations are part of the compared text
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub schema: Option<String>,
+    /// regex replacements for volatile parts of header values, json strings or the body text
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub mask: Vec<Mask>,
+    /// arrays compared regardless of order, object keys are always compared sorted
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub unordered: Vec<Unordered>,
+    /// numeric tolerance and type leniency when json bodies are compared
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub compare: Compare,
+    /// jq filter like `.items | map({id, name})` applied to a json body after `skip_body`,
+    /// a filter with several outputs gives an array of them
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub transform: Option<String>,
 }
 
 impl ResponseProfile {
@@ -94,6 +260,70 @@ impl ResponseProfile {
         Self {
             skip_headers,
             skip_body,
+            ..Default::default()
+        }
+    }
+
+    /// Normalize a json body before it's compared
+    pub fn filter_json(&self, json: &mut serde_json::Value) -> anyhow::Result<()> {
+        self.graphql.filter(json);
+        for key in &self.skip_body {
+            match json.as_object_mut() {
+                Some(obj) if obj.contains_key(key) => {
+                    obj.remove(key);
+                }
+                // a key that isn't a valid path can only be a literal one
+                _ => {
+                    if let std::result::Result::Ok(path) = key.parse::<JsonPath>() {
+                        path.remove(json);
+                    }
+                }
+            }
+        }
+        for mask in &self.mask {
+            mask.apply_json(json)?;
+        }
+        let mut unordered: Vec<_> = self.unordered.iter().collect();
+        unordered.sort_by_key(|u| std::cmp::Reverse(u.depth()));
+        for u in unordered {
+            u.sort(json)?;
+        }
+        if let Some(code) = self.transform.as_ref() {
+            *json = Jq::new(code)?.transform(json.take())?;
+        }
+        Ok(())
+    }
+
+    /// Violations of the `schema` by a body, empty if there's no schema
+    pub fn schema_violations(&self, body: &str) -> anyhow::Result<Vec<String>> {
+        let schema = match self.schema.as_ref() {
+            Some(schema) => Schema::load_cached(schema)?,
+            None => return Ok(vec![]),
+        };
+        match serde_json::from_str(body) {
+            std::result::Result::Ok(json) => schema.validate(&json),
+            Err(_) => Ok(vec!["$: body is not json".to_string()]),
+        }
+    }
+}
+
+impl ValidateConfig for ResponseProfile {
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(schema) = self.schema.as_ref() {
+            Schema::load(schema).with_context(|| format!("schema error [{}]", schema))?;
+        }
+        if let Some(code) = self.transform.as_ref() {
+            Jq::new(code).context("transform error")?;
         }
+        for mask in &self.mask {
+            mask.validate()
+                .with_context(|| format!("mask error [{}]", mask.pattern))?;
+        }
+        for u in &self.unordered {
+            u.validate()
+                .with_context(|| format!("unordered error [{}]", u.path))?;
+        }
+        self.compare.validate().context("compare error")?;
+        Ok(())
     }
 }
@@ -1,11 +1,15 @@
-use super::{LoadConfig, ValidateConfig};
-use crate::RequestProfile;
-use anyhow::Context;
+use super::{LoadConfig, Scenario, ValidateConfig};
+use crate::{ExtraArgs, RequestProfile};
+use anyhow::{anyhow, Context};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::Write;
+use std::time::Instant;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ReqConfig {
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub scenarios: HashMap<String, Scenario>,
     #[serde(flatten)]
     pub profiles: HashMap<String, RequestProfile>,
 }
@@ -19,6 +23,11 @@ impl ValidateConfig for ReqConfig {
                 .validate()
                 .with_context(|| format!("profile error [{}]", name.to_string()))?;
         }
+        for (name, scenario) in &self.scenarios {
+            scenario
+                .validate()
+                .with_context(|| format!("scenario error [{}]", name))?;
+        }
         Ok(())
     }
 }
@@ -28,7 +37,115 @@ impl ReqConfig {
         self.profiles.get(name)
     }
 
+    pub fn get_scenario(&self, name: &str) -> Option<&Scenario> {
+        self.scenarios.get(name)
+    }
+
     pub fn new(profiles: HashMap<String, RequestProfile>) -> Self {
-        Self { profiles }
+        Self {
+            scenarios: HashMap::new(),
+            profiles,
+        }
+    }
+
+    /// Send the named profiles, or all of them, and check their `expect`, printing
+    /// the result of each run and a summary, fails if any run failed.
+    /// Profiles without `expect` assert nothing, they are skipped
+    pub async fn test(&self, names: &[String], args: &ExtraArgs) -> anyhow::Result<()> {
+        let mut all: Vec<_> = match names.is_empty() {
+            true => self.profiles.keys().collect(),
+            false => names.iter().collect(),
+        };
+        all.sort();
+        let stdout = std::io::stdout();
+        let (mut pass, mut fail, mut error, mut skipped) = (0, 0, 0, 0);
+        for name in all {
+            let profile = self
+                .get_profile(name)
+                .ok_or_else(|| anyhow!("no profile {}", name))?;
+            let expect = match profile.expect.as_ref() {
+                Some(expect) => expect,
+                None => {
+                    skipped += 1;
+                    writeln!(stdout.lock(), "[{}] skipped, no expect", name)?;
+                    continue;
+                }
+            };
+            for (title, profile) in profile.expand(&[])? {
+                let title = match title.is_empty() {
+                    true => name.clone(),
+                    false => format!("{} {}", name, title),
+                };
+                let started = Instant::now();
+                let result = async {
+                    let res = profile.send(args).await?;
+                    let latency = started.elapsed();
+                    let failures = expect.check(res, latency).await?;
+                    anyhow::Ok((latency, failures))
+                }
+                .await;
+                let mut stdout = stdout.lock();
+                match result {
+                    Ok((latency, failures)) if failures.is_empty() => {
+                        pass += 1;
+                        writeln!(stdout, "[{}] pass ({}ms)", title, latency.as_millis())?;
+                    }
+                    Ok((_, failures)) => {
+                        fail += 1;
+                        writeln!(stdout, "[{}] fail", title)?;
+                        for failure in failures {
+                            writeln!(stdout, "  - {}", failure)?;
+                        }
+                    }
+                    Err(e) => {
+                        error += 1;
+                        writeln!(stdout, "[{}] error: {:#}", title, e)?;
+                    }
+                }
+            }
+        }
+        writeln!(
+            stdout.lock(),
+            "{} runs: {} pass, {} fail, {} error, {} skipped",
+            pass + fail + error,
+            pass,
+            fail,
+            error,
+            skipped
+        )?;
+        if fail + error > 0 {
+            return Err(anyhow!("{} runs failed", fail + error));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::mock;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn t1() {
+        let unasserted = mock("GET", "/test/unasserted")
+            .with_status(500)
+            .expect(0)
+            .create();
+        let _asserted = mock("GET", "/test/asserted").with_status(200).create();
+        let url = mockito::server_url();
+        let config: ReqConfig = serde_yaml::from_str(&format!(
+            r#"
+unasserted:
+  url: {url}/test/unasserted
+asserted:
+  url: {url}/test/asserted
+  expect:
+    status: 200
+"#
+        ))
+        .unwrap();
+        config.test(&[], &Default::default()).await.unwrap();
+        unasserted.assert();
     }
 }
@@ -0,0 +1,175 @@
+use anyhow::{anyhow, Result};
+use cookie_store::{CookieStore, RawCookie};
+use reqwest::header::HeaderValue;
+use std::fmt::Write as _;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// Cookie jar shared by the requests of a profile run
+#[derive(Debug, Default)]
+pub struct CookieJar(RwLock<CookieStore>);
+
+impl CookieJar {
+    /// Load a jar saved by `save`, an empty jar if the file doesn't exist yet
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = std::fs::File::open(path)?;
+        let store = CookieStore::load_json(BufReader::new(file))
+            .map_err(|e| anyhow!("invalid cookie jar {}: {}", path.display(), e))?;
+        Ok(Self(RwLock::new(store)))
+    }
+
+    /// Save the jar, session cookies included, so the next run can pick it up
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        self.0
+            .read()
+            .unwrap()
+            .save_incl_expired_and_nonpersistent_json(&mut file)
+            .map_err(|e| anyhow!("save cookie jar error: {}", e))?;
+        Ok(())
+    }
+
+    pub fn add(&self, name: &str, value: &str, url: &Url) {
+        self.add_cookie_str(&format!("{}={}", name, value), url);
+    }
+
+    pub fn add_cookie_str(&self, cookie: &str, url: &Url) {
+        let cookies = RawCookie::parse(cookie)
+            .ok()
+            .map(|c| c.into_owned())
+            .into_iter();
+        self.0.write().unwrap().store_response_cookies(cookies, url);
+    }
+
+    /// Seed the jar from a Netscape cookies file (as written by curl or browsers)
+    pub fn load_netscape(&self, content: &str) -> Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        for line in content.lines() {
+            let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+                Some(line) => (line, true),
+                None => (line, false),
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 7 {
+                return Err(anyhow!("invalid netscape cookie line: {}", line));
+            }
+            let (domain, subdomains, path, secure, expires, name, value) = (
+                fields[0], fields[1], fields[2], fields[3], fields[4], fields[5], fields[6],
+            );
+            let expires: u64 = expires
+                .parse()
+                .map_err(|_| anyhow!("invalid cookie expiration: {}", expires))?;
+            if expires != 0 && expires <= now {
+                continue;
+            }
+
+            let host = domain.trim_start_matches('.');
+            let url = Url::parse(&format!("https://{}{}", host, path))?;
+            let mut cookie = format!("{}={}; Path={}", name, value, path);
+            if subdomains.eq_ignore_ascii_case("TRUE") {
+                write!(&mut cookie, "; Domain={}", host)?;
+            }
+            if secure.eq_ignore_ascii_case("TRUE") {
+                cookie.push_str("; Secure");
+            }
+            if http_only {
+                cookie.push_str("; HttpOnly");
+            }
+            if expires != 0 {
+                write!(&mut cookie, "; Max-Age={}", expires - now)?;
+            }
+            self.add_cookie_str(&cookie, &url);
+        }
+        Ok(())
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookies = cookie_headers.filter_map(|v| {
+            v.to_str()
+                .ok()
+                .and_then(|v| RawCookie::parse(v).ok())
+                .map(|c| c.into_owned())
+        });
+        self.0.write().unwrap().store_response_cookies(cookies, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let s = self
+            .0
+            .read()
+            .unwrap()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if s.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&s).ok()
+    }
+}
+
+/// Render a `Set-Cookie` value with one attribute per line, attribute order normalized
+pub fn get_set_cookie_text(value: &str) -> Result<String> {
+    let mut output = String::new();
+    let mut parts = value.split(';').map(str::trim).filter(|v| !v.is_empty());
+    writeln!(&mut output, "{:?}", parts.next().unwrap_or_default())?;
+    let mut attrs: Vec<(String, Option<&str>)> = parts
+        .map(|attr| match attr.split_once('=') {
+            Some((k, v)) => (k.trim().to_ascii_lowercase(), Some(v.trim())),
+            None => (attr.to_ascii_lowercase(), None),
+        })
+        .collect();
+    attrs.sort();
+    for (k, v) in attrs {
+        match v {
+            Some(v) => writeln!(&mut output, "  {}: {:?}", k, v)?,
+            None => writeln!(&mut output, "  {}", k)?,
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::cookie::CookieStore as _;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let text = get_set_cookie_text("sid=abc; Path=/; HttpOnly; domain=example.com").unwrap();
+        assert_eq!(
+            text,
+            "\"sid=abc\"\n  domain: \"example.com\"\n  httponly\n  path: \"/\"\n"
+        );
+    }
+
+    #[test]
+    fn t2() {
+        let jar = CookieJar::default();
+        let content = "# Netscape HTTP Cookie File\n\
+            example.com\tFALSE\t/\tFALSE\t0\tsid\tabc\n\
+            #HttpOnly_.example.com\tTRUE\t/api\tFALSE\t0\ttoken\txyz\n\
+            example.com\tFALSE\t/\tFALSE\t1\told\tgone\n";
+        jar.load_netscape(content).unwrap();
+
+        let url = Url::parse("https://example.com/").unwrap();
+        assert_eq!(jar.cookies(&url).unwrap(), "sid=abc");
+        let url = Url::parse("https://www.example.com/api/v1").unwrap();
+        assert_eq!(jar.cookies(&url).unwrap(), "token=xyz");
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{FilePart, RequestProfile};
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use reqwest::{
+    header::{self, HeaderName, HeaderValue},
+    Method,
+};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// Flags without a value that don't change the request
+const IGNORED_FLAGS: &[&str] = &[
+    "--compressed",
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-L",
+    "--location",
+    "-k",
+    "--insecure",
+    "-i",
+    "--include",
+    "-v",
+    "--verbose",
+    "-f",
+    "--fail",
+];
+
+/// Options with a value that don't change the request
+const IGNORED_OPTIONS: &[&str] = &[
+    "-o",
+    "--output",
+    "-m",
+    "--max-time",
+    "--connect-timeout",
+    "-w",
+    "--write-out",
+    "--retry",
+];
+
+#[derive(Debug, Default)]
+struct Curl {
+    method: Option<Method>,
+    url: Option<String>,
+    headers: Vec<(String, String)>,
+    data: Vec<String>,
+    /// `@file` of a single `-d`/`--data-binary`
+    data_file: Option<String>,
+    form: Vec<(String, String)>,
+    get: bool,
+}
+
+/// Parse a curl command line like the ones copied from browser devtools
+pub fn parse_curl(cmd: &str) -> Result<RequestProfile> {
+    // line continuations of multiline commands
+    let cmd = cmd.replace("\\\r\n", " ").replace("\\\n", " ");
+    let words = shell_words::split(&cmd)?;
+    let mut words = words.into_iter().peekable();
+    if words.peek().map(|v| v.as_str()) == Some("curl") {
+        words.next();
+    }
+
+    let mut curl = Curl::default();
+    while let Some(word) = words.next() {
+        if !word.starts_with('-') || word == "-" {
+            set_url(&mut curl, word)?;
+            continue;
+        }
+        // `--data=a` and `-XPOST` forms
+        let (name, inline) = match word.split_once('=') {
+            Some((name, value)) if word.starts_with("--") => (name.to_string(), Some(value)),
+            _ if !word.starts_with("--") && word.len() > 2 => {
+                let (name, value) = word.split_at(2);
+                match IGNORED_FLAGS.contains(&name) && short_flags(&word[1..]) {
+                    true => continue,
+                    false => (name.to_string(), Some(value)),
+                }
+            }
+            _ => (word.clone(), None),
+        };
+        if IGNORED_FLAGS.contains(&name.as_str()) && inline.is_none() {
+            continue;
+        }
+        let mut value = || -> Result<String> {
+            match inline {
+                Some(v) => Ok(v.to_string()),
+                None => words
+                    .next()
+                    .ok_or_else(|| anyhow!("missing value of curl option {}", name)),
+            }
+        };
+        match name.as_str() {
+            "-X" | "--request" => curl.method = Some(value()?.parse()?),
+            "-H" | "--header" => {
+                let value = value()?;
+                let (k, v) = value
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("invalid curl header: {}", value))?;
+                curl.headers
+                    .push((k.trim().to_string(), v.trim().to_string()));
+            }
+            "-d" | "--data" | "--data-ascii" | "--data-binary" => {
+                let value = value()?;
+                match value.strip_prefix('@') {
+                    Some(path) => curl.data_file = Some(path.to_string()),
+                    None => curl.data.push(value),
+                }
+            }
+            "--data-raw" => curl.data.push(value()?),
+            "--data-urlencode" => curl.data.push(urlencode_data(&value()?)),
+            "-F" | "--form" => {
+                let value = value()?;
+                let (k, v) = value
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("invalid curl form field: {}", value))?;
+                curl.form.push((k.to_string(), v.to_string()));
+            }
+            "-u" | "--user" => {
+                let token = base64::engine::general_purpose::STANDARD.encode(value()?);
+                curl.headers
+                    .push(("authorization".to_string(), format!("Basic {}", token)));
+            }
+            "-A" | "--user-agent" => curl.headers.push(("user-agent".to_string(), value()?)),
+            "-e" | "--referer" => curl.headers.push(("referer".to_string(), value()?)),
+            "-b" | "--cookie" => curl.headers.push(("cookie".to_string(), value()?)),
+            "-G" | "--get" => curl.get = true,
+            "--url" => set_url(&mut curl, value()?)?,
+            n if IGNORED_OPTIONS.contains(&n) => {
+                value()?;
+            }
+            _ => return Err(anyhow!("unsupported curl option: {}", name)),
+        }
+    }
+    curl.into_profile()
+}
+
+/// `-sSL` style flags that are all ignored
+fn short_flags(flags: &str) -> bool {
+    flags
+        .chars()
+        .all(|c| IGNORED_FLAGS.contains(&format!("-{}", c).as_str()))
+}
+
+fn set_url(curl: &mut Curl, url: String) -> Result<()> {
+    if curl.url.is_some() {
+        return Err(anyhow!("only one url is supported: {}", url));
+    }
+    curl.url = Some(url);
+    Ok(())
+}
+
+/// `--data-urlencode` value: `content`, `=content` or `name=content`
+fn urlencode_data(value: &str) -> String {
+    let encode = |v: &str| url::form_urlencoded::byte_serialize(v.as_bytes()).collect::<String>();
+    match value.split_once('=') {
+        Some(("", content)) => encode(content),
+        Some((name, content)) => format!("{}={}", name, encode(content)),
+        None => encode(value),
+    }
+}
+
+impl Curl {
+    fn into_profile(self) -> Result<RequestProfile> {
+        let url = self
+            .url
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing curl url"))?;
+        let mut url = url.clone();
+        if self.get && !self.data.is_empty() {
+            let sep = if url.contains('?') { '&' } else { '?' };
+            url = format!("{}{}{}", url, sep, self.data.join("&"));
+        }
+        let mut profile: RequestProfile = url.parse()?;
+        if profile
+            .params
+            .as_ref()
+            .and_then(|v| v.as_object())
+            .is_some_and(|v| v.is_empty())
+        {
+            profile.params = None;
+        }
+        for (k, v) in &self.headers {
+            profile
+                .headers
+                .append(HeaderName::from_str(k)?, HeaderValue::from_str(v)?);
+        }
+
+        let has_body = !self.get
+            && (!self.data.is_empty() || self.data_file.is_some() || !self.form.is_empty());
+        profile.method = match self.method {
+            Some(method) => method,
+            None if has_body => Method::POST,
+            None => Method::GET,
+        };
+        if !has_body {
+            return Ok(profile);
+        }
+
+        if !self.form.is_empty() {
+            if !self.data.is_empty() || self.data_file.is_some() {
+                return Err(anyhow!("curl -F can't be mixed with -d"));
+            }
+            let mut fields = serde_json::Map::new();
+            for (k, v) in self.form {
+                match v.strip_prefix('@') {
+                    Some(part) => {
+                        profile.files.insert(k, FilePart::from_str(part)?);
+                    }
+                    None => {
+                        fields.insert(k, Value::String(v));
+                    }
+                }
+            }
+            if !fields.is_empty() {
+                profile.body = Some(Value::Object(fields));
+            }
+            profile.headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("multipart/form-data"),
+            );
+            return Ok(profile);
+        }
+
+        // curl posts data as a form unless told otherwise
+        if !profile.headers.contains_key(header::CONTENT_TYPE) {
+            profile.headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/x-www-form-urlencoded"),
+            );
+        }
+        if let Some(path) = self.data_file {
+            if !self.data.is_empty() {
+                return Err(anyhow!("curl -d @file can't be mixed with other data"));
+            }
+            profile.body_file = Some(path);
+            return Ok(profile);
+        }
+        // json is kept as a value, other data is sent verbatim
+        let data = self.data.join("&");
+        let ct = profile.headers[header::CONTENT_TYPE].to_str()?;
+        let mime = ct.split(';').next().unwrap_or_default().trim();
+        profile.body = Some(match mime {
+            n if n == mime::APPLICATION_JSON || n.ends_with("+json") => {
+                serde_json::from_str(&data)?
+            }
+            _ => Value::String(data),
+        });
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let cmd = r#"curl 'https://example.com/todos?id=1' \
+  -X PUT \
+  -H 'Content-Type: application/json' \
+  -H "x-id: 7" \
+  --data-raw '{"title": "hi"}' \
+  -u user:pass --compressed -sS"#;
+        let profile = parse_curl(cmd).unwrap();
+        assert_eq!(profile.method, Method::PUT);
+        assert_eq!(profile.url.as_str(), "https://example.com/todos");
+        assert_eq!(profile.params, Some(json!({"id": "1"})));
+        assert_eq!(profile.headers["x-id"], "7");
+        assert_eq!(profile.headers["authorization"], "Basic dXNlcjpwYXNz");
+        assert_eq!(profile.body, Some(json!({"title": "hi"})));
+
+        let profile = parse_curl("curl -d a=1 --data-urlencode 'b=x y' http://a.com").unwrap();
+        assert_eq!(profile.method, Method::POST);
+        assert_eq!(profile.body, Some(json!("a=1&b=x+y")));
+
+        let profile = parse_curl(r#"curl -d '{"title":"x"}' http://a.com/todos"#).unwrap();
+        assert_eq!(profile.body, Some(json!(r#"{"title":"x"}"#)));
+        assert_eq!(
+            profile.prepare(&Default::default()).unwrap().3,
+            br#"{"title":"x"}"#
+        );
+
+        let profile = parse_curl("curl 'http://a.com/q?a=1&a=2&b=1.50&c=null&d=1e3'").unwrap();
+        assert_eq!(
+            profile.params,
+            Some(json!({"a": ["1", "2"], "b": "1.50", "c": "null", "d": "1e3"}))
+        );
+        assert_eq!(
+            profile.get_url(&Default::default()).unwrap(),
+            "http://a.com/q?a=1&a=2&b=1.50&c=null&d=1e3"
+        );
+
+        let profile = parse_curl("curl -G -d q=rust http://a.com/search").unwrap();
+        assert_eq!(profile.method, Method::GET);
+        assert_eq!(profile.params, Some(json!({"q": "rust"})));
+        assert_eq!(profile.body, None);
+    }
+
+    #[test]
+    fn t2() {
+        let profile =
+            parse_curl("curl -F name=me -F 'avatar=@a.png;type=image/png' http://a.com/up")
+                .unwrap();
+        assert_eq!(profile.method, Method::POST);
+        assert_eq!(profile.body, Some(json!({"name": "me"})));
+        assert_eq!(
+            profile.files["avatar"].content_type.as_deref(),
+            Some("image/png")
+        );
+        assert!(parse_curl("curl --proxy x http://a.com").is_err());
+        assert!(parse_curl("curl -X").is_err());
+        assert!("curl -XDELETE http://a.com/1"
+            .parse::<RequestProfile>()
+            .is_ok_and(|p| p.method == Method::DELETE));
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{
+    config::ResponseExt, DiffConfig, DiffProfile, ExtraArgs, ReqConfig, RequestProfile,
+    ResponseProfile,
+};
+use anyhow::{anyhow, Result};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Method,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Request headers left out of imported profiles, they are set by the client
+const SKIP_REQUEST_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "content-length",
+    "accept-encoding",
+    "transfer-encoding",
+];
+
+/// Recorded response headers that don't apply to the decoded content of a HAR
+const SKIP_RESPONSE_HEADERS: &[&str] = &["content-encoding", "content-length", "transfer-encoding"];
+
+/// A HAR 1.2 file as exported by browser devtools, only the fields used here
+#[derive(Debug, Deserialize, Clone)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HarLog {
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HarEntry {
+    pub request: HarRequest,
+    pub response: HarResponse,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<HarHeader>,
+    pub post_data: Option<HarPostData>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HarResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<HarHeader>,
+    pub content: HarContent,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    #[serde(default)]
+    pub mime_type: String,
+    pub text: Option<String>,
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HarHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for Har {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+impl Har {
+    /// One profile per entry, named like `get_todos_1`
+    pub fn profiles(&self) -> Result<Vec<(String, RequestProfile)>> {
+        let mut names = HashMap::new();
+        self.log
+            .entries
+            .iter()
+            .map(|entry| {
+                let profile = entry.request.to_profile()?;
+                let name = profile_name(&profile);
+                let count = names.entry(name.clone()).or_insert(0);
+                *count += 1;
+                let name = match *count {
+                    1 => name,
+                    n => format!("{}_{}", name, n),
+                };
+                Ok((name, profile))
+            })
+            .collect()
+    }
+
+    pub fn to_diff_config(&self) -> Result<DiffConfig> {
+        let profiles = self
+            .profiles()?
+            .into_iter()
+            .map(|(name, req)| (name, DiffProfile::new(req.clone(), req, Default::default())))
+            .collect();
+        Ok(DiffConfig::new(profiles))
+    }
+
+    pub fn to_req_config(&self) -> Result<ReqConfig> {
+        Ok(ReqConfig::new(self.profiles()?.into_iter().collect()))
+    }
+
+    /// Entries whose request has one of the methods
+    pub fn entries_with(&self, methods: &[Method]) -> Vec<&HarEntry> {
+        self.log
+            .entries
+            .iter()
+            .filter(|e| {
+                methods
+                    .iter()
+                    .any(|m| m.as_str().eq_ignore_ascii_case(&e.request.method))
+            })
+            .collect()
+    }
+
+    /// Send each recorded request with one of the methods and diff the recorded response
+    /// with the live one
+    pub async fn diff(
+        &self,
+        args: &ExtraArgs,
+        response: &ResponseProfile,
+        methods: &[Method],
+    ) -> Result<String> {
+        let entries = self.entries_with(methods);
+        let skipped = self.log.entries.len() - entries.len();
+        if skipped > 0 {
+            eprintln!("skipped {} entries with other methods", skipped);
+        }
+        let stdout = std::io::stdout();
+        let (mut pass, mut diff, mut error) = (0, 0, 0);
+        for entry in entries {
+            let title = format!("{} {}", entry.request.method, entry.request.url);
+            let result = async {
+                let recorded = entry.response.to_response()?;
+                let live = entry.request.to_profile()?.send(args).await?;
+                recorded.diff(live, response).await
+            }
+            .await;
+            let mut stdout = stdout.lock();
+            match result {
+                Ok(output) if output.is_empty() => {
+                    pass += 1;
+                    writeln!(stdout, "[{}] pass", title)?;
+                }
+                Ok(output) => {
+                    diff += 1;
+                    writeln!(stdout, "[{}] diff", title)?;
+                    write!(stdout, "{}", output)?;
+                }
+                Err(e) => {
+                    error += 1;
+                    writeln!(stdout, "[{}] error: {:#}", title, e)?;
+                }
+            }
+        }
+        writeln!(
+            stdout.lock(),
+            "{} entries: {} pass, {} diff, {} error",
+            pass + diff + error,
+            pass,
+            diff,
+            error
+        )?;
+        if error > 0 {
+            return Err(anyhow!("{} entries failed", error));
+        }
+        Ok("".to_string())
+    }
+}
+
+impl HarRequest {
+    pub fn to_profile(&self) -> Result<RequestProfile> {
+        let mut profile: RequestProfile = self.url.parse()?;
+        profile.method = self.method.parse()?;
+        if profile
+            .params
+            .as_ref()
+            .and_then(|v| v.as_object())
+            .is_some_and(|v| v.is_empty())
+        {
+            profile.params = None;
+        }
+        profile.headers = to_header_map(&self.headers, SKIP_REQUEST_HEADERS)?;
+        if let Some(data) = self.post_data.as_ref().filter(|v| !v.text.is_empty()) {
+            let mime = data.mime_type.split(';').next().unwrap_or_default().trim();
+            profile.body = Some(match mime {
+                "application/json" => serde_json::from_str(&data.text)?,
+                "application/x-www-form-urlencoded" => Value::Object(
+                    serde_urlencoded::from_str::<Vec<(String, String)>>(&data.text)?
+                        .into_iter()
+                        .map(|(k, v)| (k, Value::String(v)))
+                        .collect(),
+                ),
+                _ => Value::String(data.text.clone()),
+            });
+            if !mime.is_empty() && !profile.headers.contains_key(reqwest::header::CONTENT_TYPE) {
+                profile
+                    .headers
+                    .insert(reqwest::header::CONTENT_TYPE, mime.parse()?);
+            }
+        }
+        Ok(profile)
+    }
+}
+
+impl HarResponse {
+    fn to_response(&self) -> Result<ResponseExt> {
+        if self.content.encoding.as_deref() == Some("base64") {
+            return Err(anyhow!("base64 encoded content is not supported"));
+        }
+        // the version is left as http/1.1, the one the live client speaks
+        let mut builder = http::Response::builder().status(self.status);
+        for (k, v) in to_header_map(&self.headers, SKIP_RESPONSE_HEADERS)?.iter() {
+            builder = builder.header(k, v);
+        }
+        let body = self.content.text.clone().unwrap_or_default();
+        let res: reqwest::Response = builder.body(body)?.into();
+        Ok(res.into())
+    }
+}
+
+fn to_header_map(headers: &[HarHeader], skip: &[&str]) -> Result<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for header in headers {
+        let name = header.name.to_lowercase();
+        // http/2 pseudo headers like `:authority`
+        if name.starts_with(':') || skip.contains(&name.as_str()) {
+            continue;
+        }
+        map.append(
+            HeaderName::from_str(&name)?,
+            HeaderValue::from_str(&header.value)?,
+        );
+    }
+    Ok(map)
+}
+
+pub(crate) fn profile_name(profile: &RequestProfile) -> String {
+    let mut name = profile.method.as_str().to_lowercase();
+    for part in profile
+        .url
+        .path()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+    {
+        if !part.is_empty() {
+            name.push('_');
+            name.push_str(part);
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let har: Har = std::fs::read_to_string("fixtures/todo.har")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let profiles = har.profiles().unwrap();
+        let names: Vec<_> = profiles.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["get_todos_1", "post_todos", "get_todos_1_2"]);
+        let (_, get) = &profiles[0];
+        assert_eq!(get.params, Some(json!({"a": "1"})));
+        assert_eq!(get.headers["accept"], "application/json");
+        assert!(!get.headers.contains_key("accept-encoding"));
+        let (_, post) = &profiles[1];
+        assert_eq!(post.method, reqwest::Method::POST);
+        assert_eq!(post.body, Some(json!({"title": "hi"})));
+        assert_eq!(har.to_diff_config().unwrap().profiles.len(), 3);
+        assert_eq!(har.entries_with(&[reqwest::Method::GET]).len(), 2);
+        assert_eq!(har.entries_with(&[reqwest::Method::POST]).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn t2() {
+        let har: Har = std::fs::read_to_string("fixtures/todo.har")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let res = har.log.entries[0].response.to_response().unwrap();
+        let text = res.filter_text(&ResponseProfile::default()).await.unwrap();
+        assert!(text.starts_with("HTTP/1.1 200 OK\n"));
+        assert!(text.contains("\"title\": \"delectus aut autem\""));
+    }
+}
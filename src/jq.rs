@@ -0,0 +1,97 @@
+use anyhow::{anyhow, Result};
+use jaq_core::{
+    load::{self, Arena, File, Loader},
+    Compiler, Ctx, Native, RcIter,
+};
+use jaq_json::Val;
+use serde_json::Value;
+
+/// A compiled jq filter like `.items | map({id, name})`, run in-process
+pub struct Jq {
+    filter: jaq_core::Filter<Native<Val>>,
+}
+
+impl Jq {
+    pub fn new(code: &str) -> Result<Self> {
+        let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+        let arena = Arena::default();
+        let modules = loader
+            .load(&arena, File { code, path: () })
+            .map_err(|errors| {
+                let reasons: Vec<_> = errors
+                    .into_iter()
+                    .flat_map(|(_, e)| load_error(e))
+                    .collect();
+                anyhow!("invalid jq filter {}: {}", code, reasons.join(", "))
+            })?;
+        let filter = Compiler::default()
+            .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+            .compile(modules)
+            .map_err(|errors| {
+                let reasons: Vec<_> = errors
+                    .into_iter()
+                    .flat_map(|(_, e)| e)
+                    .map(|(name, undefined)| format!("undefined {} {}", undefined.as_str(), name))
+                    .collect();
+                anyhow!("invalid jq filter {}: {}", code, reasons.join(", "))
+            })?;
+        Ok(Self { filter })
+    }
+
+    /// Every output of the filter
+    pub fn run(&self, input: Value) -> Result<Vec<Value>> {
+        let inputs = RcIter::new(core::iter::empty());
+        self.filter
+            .run((Ctx::new([], &inputs), Val::from(input)))
+            .map(|v| v.map(Value::from).map_err(|e| anyhow!("jq error: {}", e)))
+            .collect()
+    }
+
+    /// The only output of the filter, or an array of all of them
+    pub fn transform(&self, input: Value) -> Result<Value> {
+        let mut outputs = self.run(input)?;
+        match outputs.len() {
+            1 => Ok(outputs.remove(0)),
+            _ => Ok(Value::Array(outputs)),
+        }
+    }
+}
+
+fn load_error(error: load::Error<&str>) -> Vec<String> {
+    let found = |s: &str| s.chars().take(10).collect::<String>();
+    match error {
+        load::Error::Io(errors) => errors.into_iter().map(|(_, e)| e).collect(),
+        load::Error::Lex(errors) => errors
+            .into_iter()
+            .map(|(expect, s)| format!("expected {} at `{}`", expect.as_str(), found(s)))
+            .collect(),
+        load::Error::Parse(errors) => errors
+            .into_iter()
+            .map(|(expect, s)| format!("expected {} at `{}`", expect.as_str(), found(s)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let body = json!({"items": [{"id": 1, "name": "a", "at": 1}, {"id": 2, "name": "b"}]});
+        let jq = Jq::new(".items | map({id, name}) | sort_by(.id) | reverse").unwrap();
+        assert_eq!(
+            jq.transform(body.clone()).unwrap(),
+            json!([{"id": 2, "name": "b"}, {"id": 1, "name": "a"}])
+        );
+        let jq = Jq::new(".items[].id").unwrap();
+        assert_eq!(jq.run(body.clone()).unwrap(), vec![json!(1), json!(2)]);
+        assert_eq!(jq.transform(body).unwrap(), json!([1, 2]));
+
+        assert!(Jq::new(".items | map(").is_err());
+        assert!(Jq::new("nope").is_err());
+        assert!(Jq::new(".a + 1").unwrap().run(json!({"a": "x"})).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// A simple JSON path like `$.data.items[0].id`, the leading `$` is optional,
+/// `[*]` or `.*` matches every item of an array or object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath(Vec<PathSegment>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+impl JsonPath {
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0
+    }
+
+    /// The first value matching the path
+    pub fn get<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.select(value).into_iter().next()
+    }
+
+    /// All values matching the path
+    pub fn select<'a>(&self, value: &'a Value) -> Vec<&'a Value> {
+        let mut values = vec![value];
+        for seg in &self.0 {
+            values = values
+                .into_iter()
+                .flat_map(|v| -> Vec<&Value> {
+                    match (seg, v) {
+                        (PathSegment::Key(k), v) => v.get(k).into_iter().collect(),
+                        (PathSegment::Index(i), v) => v.get(i).into_iter().collect(),
+                        (PathSegment::Wildcard, Value::Array(arr)) => arr.iter().collect(),
+                        (PathSegment::Wildcard, Value::Object(obj)) => obj.values().collect(),
+                        _ => vec![],
+                    }
+                })
+                .collect();
+        }
+        values
+    }
+
+    /// Whether a concrete path of keys and indexes is matched by this one
+    pub fn matches(&self, path: &[PathSegment]) -> bool {
+        self.0.len() == path.len()
+            && self.0.iter().zip(path).all(|(seg, p)| match (seg, p) {
+                (PathSegment::Wildcard, _) => true,
+                (seg, p) => seg == p,
+            })
+    }
+
+    /// All values matching the path, to change them in place
+    pub fn select_mut<'a>(&self, value: &'a mut Value) -> Vec<&'a mut Value> {
+        let mut values = vec![value];
+        for seg in &self.0 {
+            values = values
+                .into_iter()
+                .flat_map(|v| -> Vec<&mut Value> {
+                    match (seg, v) {
+                        (PathSegment::Key(k), v) => v.get_mut(k).into_iter().collect(),
+                        (PathSegment::Index(i), v) => v.get_mut(i).into_iter().collect(),
+                        (PathSegment::Wildcard, Value::Array(arr)) => arr.iter_mut().collect(),
+                        (PathSegment::Wildcard, Value::Object(obj)) => obj.values_mut().collect(),
+                        _ => vec![],
+                    }
+                })
+                .collect();
+        }
+        values
+    }
+
+    /// Set the value at the path, creating missing objects and arrays on the way
+    pub fn set(&self, value: &mut Value, new: Value) -> Result<()> {
+        let mut current = value;
+        for seg in &self.0 {
+            if current.is_null() {
+                *current = match seg {
+                    PathSegment::Index(_) => Value::Array(vec![]),
+                    _ => Value::Object(Default::default()),
+                };
+            }
+            current = match (seg, current) {
+                (PathSegment::Key(k), Value::Object(obj)) => {
+                    obj.entry(k.clone()).or_insert(Value::Null)
+                }
+                (PathSegment::Index(i), Value::Array(arr)) if *i <= arr.len() => {
+                    if *i == arr.len() {
+                        arr.push(Value::Null);
+                    }
+                    &mut arr[*i]
+                }
+                _ => return Err(anyhow!("can't set {} at {}", self, seg_text(seg))),
+            };
+        }
+        *current = new;
+        Ok(())
+    }
+
+    /// Remove all values matching the path
+    pub fn remove(&self, value: &mut Value) {
+        if let Some((last, parents)) = self.0.split_last() {
+            remove_in(value, parents, last);
+        }
+    }
+}
+
+fn remove_in(value: &mut Value, parents: &[PathSegment], last: &PathSegment) {
+    match parents.split_first() {
+        Some((seg, rest)) => match (seg, value) {
+            (PathSegment::Key(k), v) => {
+                if let Some(v) = v.get_mut(k) {
+                    remove_in(v, rest, last)
+                }
+            }
+            (PathSegment::Index(i), v) => {
+                if let Some(v) = v.get_mut(i) {
+                    remove_in(v, rest, last)
+                }
+            }
+            (PathSegment::Wildcard, Value::Array(arr)) => {
+                arr.iter_mut().for_each(|v| remove_in(v, rest, last))
+            }
+            (PathSegment::Wildcard, Value::Object(obj)) => {
+                obj.values_mut().for_each(|v| remove_in(v, rest, last))
+            }
+            _ => {}
+        },
+        None => match (last, value) {
+            (PathSegment::Key(k), Value::Object(obj)) => {
+                obj.remove(k);
+            }
+            (PathSegment::Index(i), Value::Array(arr)) if *i < arr.len() => {
+                arr.remove(*i);
+            }
+            (PathSegment::Wildcard, Value::Array(arr)) => arr.clear(),
+            (PathSegment::Wildcard, Value::Object(obj)) => obj.clear(),
+            _ => {}
+        },
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = s.trim();
+        let path = path.strip_prefix('$').unwrap_or(path);
+        let mut segments = vec![];
+        let mut chars = path.chars().peekable();
+        let mut key = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => {
+                    if !key.is_empty() {
+                        segments.push(push_key(std::mem::take(&mut key)));
+                    }
+                }
+                '[' => {
+                    if !key.is_empty() {
+                        segments.push(push_key(std::mem::take(&mut key)));
+                    }
+                    let mut inner = String::new();
+                    for c in chars.by_ref() {
+                        if c == ']' {
+                            break;
+                        }
+                        inner.push(c);
+                    }
+                    let inner = inner.trim();
+                    let seg = match inner.parse::<usize>() {
+                        Ok(i) => PathSegment::Index(i),
+                        Err(_) if inner == "*" => PathSegment::Wildcard,
+                        Err(_)
+                            if inner.len() >= 2
+                                && (inner.starts_with('"') || inner.starts_with('\'')) =>
+                        {
+                            PathSegment::Key(inner[1..inner.len() - 1].to_string())
+                        }
+                        Err(_) => return Err(anyhow!("invalid json path: {}", s)),
+                    };
+                    segments.push(seg);
+                    if !matches!(chars.peek(), None | Some('.') | Some('[')) {
+                        return Err(anyhow!("invalid json path: {}", s));
+                    }
+                }
+                c => key.push(c),
+            }
+        }
+        if !key.is_empty() {
+            segments.push(push_key(key));
+        }
+        Ok(Self(segments))
+    }
+}
+
+fn push_key(key: String) -> PathSegment {
+    match key.as_str() {
+        "*" => PathSegment::Wildcard,
+        _ => PathSegment::Key(key),
+    }
+}
+
+fn seg_text(seg: &PathSegment) -> String {
+    match seg {
+        PathSegment::Key(k) => format!(".{}", k),
+        PathSegment::Index(i) => format!("[{}]", i),
+        PathSegment::Wildcard => "[*]".to_string(),
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "$")?;
+        for seg in &self.0 {
+            write!(f, "{}", seg_text(seg))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let path: JsonPath = "$.data.items[1]['id']".parse().unwrap();
+        assert_eq!(path.to_string(), "$.data.items[1].id");
+        let value = json!({"data": {"items": [{"id": 1}, {"id": 2}]}});
+        assert_eq!(path.get(&value), Some(&json!(2)));
+        let path: JsonPath = "data.missing".parse().unwrap();
+        assert_eq!(path.get(&value), None);
+        assert!("a[0]b".parse::<JsonPath>().is_err());
+    }
+
+    #[test]
+    fn t2() {
+        let mut value = json!({"errors": [{"message": "a", "locations": [1]}, {"message": "b"}]});
+        let path: JsonPath = "errors[*].message".parse().unwrap();
+        assert_eq!(path.select(&value), vec![&json!("a"), &json!("b")]);
+        let path: JsonPath = "errors.*.locations".parse().unwrap();
+        assert_eq!(path.to_string(), "$.errors[*].locations");
+        path.remove(&mut value);
+        assert_eq!(
+            value,
+            json!({"errors": [{"message": "a"}, {"message": "b"}]})
+        );
+    }
+
+    #[test]
+    fn t3() {
+        let mut value = json!({"user": {"name": "tom"}});
+        let path: JsonPath = "user.address.city".parse().unwrap();
+        path.set(&mut value, json!("Paris")).unwrap();
+        let path: JsonPath = "tags[0]".parse().unwrap();
+        path.set(&mut value, json!("x")).unwrap();
+        assert_eq!(
+            value,
+            json!({"user": {"name": "tom", "address": {"city": "Paris"}}, "tags": ["x"]})
+        );
+        let path: JsonPath = "tags[5]".parse().unwrap();
+        assert!(path.set(&mut value, json!(1)).is_err());
+        let path: JsonPath = "user.name.first".parse().unwrap();
+        assert!(path.set(&mut value, json!(1)).is_err());
+    }
+}
@@ -1,16 +1,120 @@
 pub mod cli;
+mod collection;
 mod config;
+mod cookie;
+mod curl;
+mod har;
+mod jq;
+mod json_path;
+mod openapi;
+mod proxy;
+mod replay;
+mod schema;
+mod template;
 mod utils;
 
+pub use collection::{InsomniaExport, PostmanCollection};
 pub use config::{
-    get_body_text, get_header_text, get_status_text, DiffConfig, DiffProfile, LoadConfig,
-    ReqConfig, RequestProfile, ResponseProfile,
+    get_body_text, get_filtered_body_text, get_filtered_header_text, get_header_text,
+    get_status_text, load_data, Assertion, Capture, Check, CommandHook, Compare, CookieProfile,
+    DiffConfig, DiffProfile, DiffScenario, Expect, ExpectStatus, ExportFormat, FilePart,
+    GraphqlRequest, GraphqlResponse, Hook, LoadConfig, Mask, Matrix, QueryFormat, ReqConfig,
+    RequestProfile, ResponseProfile, Scenario, ScenarioStep, Tolerance, Unordered,
 };
+pub use cookie::CookieJar;
+pub use curl::parse_curl;
+pub use har::{Har, HarEntry, HarRequest, HarResponse};
+pub use jq::Jq;
+pub use json_path::{JsonPath, PathSegment};
+pub use openapi::OpenApi;
+pub use proxy::Proxy;
+pub use replay::{parse_log, LogEntry, LogFormat, Replay};
+pub use schema::Schema;
+pub use template::Variables;
 pub use utils::{diff_text, highlight_text, process_error};
 
+use std::collections::BTreeMap;
+
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
 pub struct ExtraArgs {
+    /// values for `{name}` placeholders in the url path
+    pub paths: Vec<(String, String)>,
     pub headers: Vec<(String, String)>,
-    pub querys: Vec<(String, String)>,
-    pub bodys: Vec<(String, String)>,
+    /// keys are json paths like `user.tags[0]`
+    pub querys: Vec<(String, ArgValue)>,
+    pub bodys: Vec<(String, ArgValue)>,
+    /// multipart file parts, like `./avatar.png;type=image/png;filename=me.png`
+    pub files: Vec<(String, String)>,
+    /// overrides only applied to one side of a diff
+    pub sides: BTreeMap<Side, ExtraArgs>,
+}
+
+/// A side of a diff, `1:` or `2:` in `-e` arguments
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Side {
+    Req1,
+    Req2,
+}
+
+impl ExtraArgs {
+    /// The shared overrides followed by the overrides of `side`, so the latter win
+    pub fn for_side(&self, side: Side) -> ExtraArgs {
+        let mut args = self.clone();
+        args.sides.clear();
+        if let Some(extra) = self.sides.get(&side) {
+            args.paths.extend(extra.paths.iter().cloned());
+            args.headers.extend(extra.headers.iter().cloned());
+            args.querys.extend(extra.querys.iter().cloned());
+            args.bodys.extend(extra.bodys.iter().cloned());
+            args.files.extend(extra.files.iter().cloned());
+        }
+        args
+    }
+}
+
+/// The value of a query or body override
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ArgValue {
+    /// `key=value`, json if it parses as json, a string otherwise
+    Text(String),
+    /// `key:=value` or `key=@file`
+    Json(serde_json::Value),
+    /// `!key`, removes the key
+    Delete,
+}
+
+impl ArgValue {
+    pub fn to_json(&self) -> Option<serde_json::Value> {
+        match self {
+            ArgValue::Text(s) => Some(
+                s.parse()
+                    .unwrap_or_else(|_| serde_json::Value::String(s.clone())),
+            ),
+            ArgValue::Json(v) => Some(v.clone()),
+            ArgValue::Delete => None,
+        }
+    }
+
+    /// Set or remove the value at the json path `key` of `target`
+    pub fn apply(&self, key: &str, target: &mut serde_json::Value) -> anyhow::Result<()> {
+        let path: JsonPath = key.parse()?;
+        match self.to_json() {
+            Some(v) => path.set(target, v)?,
+            None => path.remove(target),
+        }
+        Ok(())
+    }
+
+    /// Set or remove the top level `key` of the object `target`, dots and brackets are
+    /// part of the key, like the `page.size` query of many APIs
+    pub fn apply_key(&self, key: &str, target: &mut serde_json::Value) -> anyhow::Result<()> {
+        let obj = target
+            .as_object_mut()
+            .ok_or_else(|| anyhow::anyhow!("can't set {} on a non-object", key))?;
+        match self.to_json() {
+            Some(v) => obj.insert(key.to_string(), v),
+            None => obj.remove(key),
+        };
+        Ok(())
+    }
 }
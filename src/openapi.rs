@@ -0,0 +1,395 @@
+use crate::{
+    har::profile_name, template::value_to_string, DiffProfile, RequestProfile, ResponseProfile,
+    Schema,
+};
+use anyhow::{anyhow, Result};
+use reqwest::{
+    header::{self, HeaderName, HeaderValue},
+    Method,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use url::Url;
+
+/// Named profiles in the order of the spec
+type Profiles = Vec<(String, DiffProfile)>;
+
+/// An OpenAPI 3 spec in yaml or json, `$ref`s are resolved against the whole document
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    /// file the spec was loaded from, imported profiles refer to its response schemas
+    pub path: Option<String>,
+    root: Value,
+    paths: BTreeMap<String, PathItem>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+struct PathItem {
+    #[serde(default)]
+    parameters: Vec<Value>,
+    get: Option<Operation>,
+    put: Option<Operation>,
+    post: Option<Operation>,
+    delete: Option<Operation>,
+    patch: Option<Operation>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct Operation {
+    operation_id: Option<String>,
+    #[serde(default)]
+    parameters: Vec<Value>,
+    request_body: Option<Value>,
+    #[serde(default)]
+    responses: BTreeMap<String, Value>,
+}
+
+impl FromStr for OpenApi {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let root: Value = serde_yaml::from_str(s)?;
+        let paths = match root.get("paths") {
+            Some(paths) => serde_json::from_value(paths.clone())?,
+            None => return Err(anyhow!("openapi spec has no paths")),
+        };
+        Ok(Self {
+            path: None,
+            root,
+            paths,
+        })
+    }
+}
+
+impl PathItem {
+    fn operations(&self) -> Vec<(Method, &Operation)> {
+        [
+            (Method::GET, &self.get),
+            (Method::PUT, &self.put),
+            (Method::POST, &self.post),
+            (Method::DELETE, &self.delete),
+            (Method::PATCH, &self.patch),
+        ]
+        .into_iter()
+        .filter_map(|(method, op)| op.as_ref().map(|op| (method, op)))
+        .collect()
+    }
+}
+
+impl OpenApi {
+    pub fn load(path: &str) -> Result<Self> {
+        let mut spec: Self = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("read openapi spec {} error: {}", path, e))?
+            .parse()?;
+        spec.path = Some(path.to_string());
+        Ok(spec)
+    }
+
+    /// Follow a local `$ref` like `#/components/parameters/Id`
+    fn resolve<'a>(&'a self, value: &'a Value) -> Result<&'a Value> {
+        match value.get("$ref").and_then(|v| v.as_str()) {
+            Some(r) => {
+                let pointer = r
+                    .strip_prefix('#')
+                    .ok_or_else(|| anyhow!("only local refs are supported: {}", r))?;
+                let target = self
+                    .root
+                    .pointer(pointer)
+                    .ok_or_else(|| anyhow!("unresolved ref: {}", r))?;
+                self.resolve(target)
+            }
+            None => Ok(value),
+        }
+    }
+
+    /// One profile per operation with one of `methods`, named by operationId.
+    /// Operations whose required params have no example or default are skipped with a reason
+    pub fn profiles(
+        &self,
+        base1: &Url,
+        base2: &Url,
+        methods: &[Method],
+    ) -> Result<(Profiles, Vec<String>)> {
+        let mut profiles = vec![];
+        let mut skipped = vec![];
+        let mut names = HashMap::new();
+        for (path, item) in &self.paths {
+            for (method, op) in item.operations() {
+                if !methods.contains(&method) {
+                    continue;
+                }
+                let req1 = match self.request(base1, path, &method, item, op)? {
+                    Ok(req) => req,
+                    Err(reason) => {
+                        skipped.push(format!("{} {}: {}", method, path, reason));
+                        continue;
+                    }
+                };
+                let mut req2 = req1.clone();
+                req2.url = join_url(base2, path)?;
+                let name = op
+                    .operation_id
+                    .clone()
+                    .unwrap_or_else(|| profile_name(&req1));
+                let count = names.entry(name.clone()).or_insert(0);
+                *count += 1;
+                let name = match *count {
+                    1 => name,
+                    n => format!("{}_{}", name, n),
+                };
+                // a schema with keywords the validator doesn't check is left out
+                let schema = match self.response_schema(path, &method, op)? {
+                    Some(spec) => match Schema::load(&spec) {
+                        Ok(_) => Some(spec),
+                        Err(e) => {
+                            skipped.push(format!("{} {} schema: {}", method, path, e));
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                let response = ResponseProfile {
+                    schema,
+                    ..Default::default()
+                };
+                let profile = DiffProfile::new(req1, req2, response);
+                profiles.push((name, profile));
+            }
+        }
+        Ok((profiles, skipped))
+    }
+
+    /// A diff config in yaml with the profiles in the order of the spec, so a regenerated
+    /// config diffs cleanly against the previous one
+    pub fn to_diff_yaml(
+        &self,
+        base1: &Url,
+        base2: &Url,
+        methods: &[Method],
+    ) -> Result<(String, Vec<String>)> {
+        let (profiles, skipped) = self.profiles(base1, base2, methods)?;
+        let mut config = serde_yaml::Mapping::new();
+        for (name, profile) in profiles {
+            config.insert(name.into(), serde_yaml::to_value(profile)?);
+        }
+        Ok((serde_yaml::to_string(&config)?, skipped))
+    }
+
+    /// The request of an operation, or why it can't be built
+    fn request(
+        &self,
+        base: &Url,
+        path: &str,
+        method: &Method,
+        item: &PathItem,
+        op: &Operation,
+    ) -> Result<std::result::Result<RequestProfile, String>> {
+        // operation params override path params with the same name and location
+        let mut params: BTreeMap<(String, String), &Value> = BTreeMap::new();
+        for param in item.parameters.iter().chain(op.parameters.iter()) {
+            let param = self.resolve(param)?;
+            let text = |k: &str| param.get(k).and_then(|v| v.as_str()).unwrap_or_default();
+            params.insert((text("in").to_string(), text("name").to_string()), param);
+        }
+
+        let mut req = RequestProfile::new(
+            method.clone(),
+            join_url(base, path)?,
+            None,
+            Default::default(),
+            None,
+        );
+        let mut query = serde_json::Map::new();
+        for ((location, name), param) in params {
+            let required =
+                location == "path" || param.get("required").and_then(|v| v.as_bool()) == Some(true);
+            let value = match self.param_value(param)? {
+                Some(value) => value,
+                None if required => {
+                    return Ok(Err(format!("no example for {} param {}", location, name)))
+                }
+                None => continue,
+            };
+            match location.as_str() {
+                "path" => {
+                    req.path_params.insert(name, value);
+                }
+                "query" => {
+                    query.insert(name, value);
+                }
+                "header" => {
+                    req.headers.insert(
+                        HeaderName::from_str(&name)?,
+                        HeaderValue::from_str(&value_to_string(&value))?,
+                    );
+                }
+                _ => {}
+            }
+        }
+        if !query.is_empty() {
+            req.params = Some(Value::Object(query));
+        }
+
+        if let Some(body) = op.request_body.as_ref() {
+            let body = self.resolve(body)?;
+            let content = body.pointer("/content/application~1json");
+            match content.map(|v| self.example(v)).transpose()?.flatten() {
+                Some(example) => {
+                    req.body = Some(example);
+                    req.headers.insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    );
+                }
+                None if body.get("required").and_then(|v| v.as_bool()) == Some(true) => {
+                    return Ok(Err("no json example for the request body".to_string()))
+                }
+                None => {}
+            }
+        }
+        Ok(Ok(req))
+    }
+
+    /// Reference to the json schema of the first 2xx response, if the spec was loaded from a file
+    fn response_schema(
+        &self,
+        path: &str,
+        method: &Method,
+        op: &Operation,
+    ) -> Result<Option<String>> {
+        let file = match self.path.as_ref() {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        let success = op.responses.iter().find(|(code, _)| code.starts_with('2'));
+        let (code, response) = match success {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        // a shared response is referred to where it's defined
+        let pointer = match response.get("$ref").and_then(|v| v.as_str()) {
+            Some(r) => r.trim_start_matches('#').to_string(),
+            None => format!(
+                "/paths/{}/{}/responses/{}",
+                escape_pointer(path),
+                method.as_str().to_lowercase(),
+                escape_pointer(code)
+            ),
+        };
+        let response = self.resolve(response)?;
+        Ok(response
+            .pointer("/content/application~1json/schema")
+            .map(|_| format!("{}#{}/content/application~1json/schema", file, pointer)))
+    }
+
+    /// The example of a parameter, or of its schema, or the schema default or first enum value
+    fn param_value(&self, param: &Value) -> Result<Option<Value>> {
+        if let Some(example) = self.example(param)? {
+            return Ok(Some(example));
+        }
+        let schema = match param.get("schema") {
+            Some(schema) => self.resolve(schema)?,
+            None => return Ok(None),
+        };
+        Ok(schema
+            .get("example")
+            .or_else(|| schema.get("default"))
+            .or_else(|| schema.get("enum").and_then(|v| v.get(0)))
+            .cloned())
+    }
+
+    /// `example`, the first of `examples`, or the example of the schema
+    fn example(&self, value: &Value) -> Result<Option<Value>> {
+        if let Some(example) = value.get("example") {
+            return Ok(Some(example.clone()));
+        }
+        if let Some(example) = value
+            .get("examples")
+            .and_then(|v| v.as_object())
+            .and_then(|v| v.values().next())
+        {
+            return Ok(self.resolve(example)?.get("value").cloned());
+        }
+        match value.get("schema") {
+            Some(schema) => Ok(self.resolve(schema)?.get("example").cloned()),
+            None => Ok(None),
+        }
+    }
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn join_url(base: &Url, path: &str) -> Result<Url> {
+    let base = base.as_str().trim_end_matches('/');
+    Ok(Url::parse(&format!("{}{}", base, path))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let spec: OpenApi = std::fs::read_to_string("fixtures/todo.openapi.yml")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let base1 = Url::parse("http://a.com/v1/").unwrap();
+        let base2 = Url::parse("http://b.com").unwrap();
+        let (profiles, skipped) = spec.profiles(&base1, &base2, &[Method::GET]).unwrap();
+        let names: Vec<_> = profiles.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["listTodos", "getTodo"]);
+        assert_eq!(
+            skipped,
+            vec!["GET /users/{userId}/albums: no example for path param userId"]
+        );
+        let (_, list) = &profiles[0];
+        assert_eq!(list.req1.params, Some(json!({"userId": 1})));
+        let (_, get) = &profiles[1];
+        assert_eq!(get.req1.path_params["id"], json!(1));
+        assert_eq!(
+            get.req1.get_url(&Default::default()).unwrap(),
+            "http://a.com/v1/todos/1"
+        );
+        assert_eq!(
+            get.req2.get_url(&Default::default()).unwrap(),
+            "http://b.com/todos/1"
+        );
+
+        assert_eq!(get.response.schema, None);
+
+        let spec = OpenApi::load("fixtures/todo.openapi.yml").unwrap();
+        let (profiles, _) = spec.profiles(&base1, &base2, &[Method::GET]).unwrap();
+        let schema = profiles[1].1.response.schema.clone().unwrap();
+        assert_eq!(
+            schema,
+            "fixtures/todo.openapi.yml#/paths/~1todos~1{id}/get/responses/200/content/application~1json/schema"
+        );
+        let todo = serde_json::json!({"id": 1, "title": "a", "completed": true});
+        assert!(crate::Schema::load(&schema)
+            .unwrap()
+            .validate(&todo)
+            .unwrap()
+            .is_empty());
+
+        let (yaml, _) = spec.to_diff_yaml(&base1, &base2, &[Method::GET]).unwrap();
+        assert!(yaml.starts_with("listTodos:\n"));
+        assert!(yaml.find("\ngetTodo:\n") > yaml.find("listTodos"));
+        let config: crate::DiffConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(config.profiles.len(), 2);
+
+        let (profiles, _) = spec.profiles(&base1, &base2, &[Method::POST]).unwrap();
+        assert_eq!(profiles[0].0, "createTodo");
+        assert_eq!(
+            profiles[0].1.req1.body,
+            Some(json!({"title": "hi", "completed": false}))
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{config::ResponseExt, ResponseProfile};
+use anyhow::Result;
+use hyper::{
+    body::Bytes,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use reqwest::{header::HeaderMap, redirect::Policy, Method};
+use std::{convert::Infallible, future::Future, io::Write, net::SocketAddr, sync::Arc};
+use url::Url;
+
+/// Headers of a single connection, not forwarded
+const HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+];
+
+/// Forwards requests to the primary upstream and mirrors them to the candidate,
+/// the responses are diffed in the background and differences are reported
+#[derive(Debug)]
+pub struct Proxy {
+    pub primary: Url,
+    pub candidate: Url,
+    pub response: ResponseProfile,
+    /// file the differences are appended to, stdout if none
+    pub report: Option<String>,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Clone)]
+struct Mirrored {
+    method: Method,
+    /// path with the query string
+    path: String,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+#[derive(Debug, Clone)]
+struct Captured {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Proxy {
+    pub fn new(
+        primary: Url,
+        candidate: Url,
+        response: ResponseProfile,
+        report: Option<String>,
+    ) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .redirect(Policy::none())
+            .build()?;
+        Ok(Self {
+            primary,
+            candidate,
+            response,
+            report,
+            client,
+        })
+    }
+
+    /// Bind `addr`, returning the bound address and the server to await
+    pub fn bind(self, addr: SocketAddr) -> Result<(SocketAddr, impl Future<Output = Result<()>>)> {
+        let proxy = Arc::new(self);
+        let make = make_service_fn(move |_| {
+            let proxy = proxy.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| proxy.clone().handle(req))) }
+        });
+        let server = Server::try_bind(&addr)?.serve(make);
+        let addr = server.local_addr();
+        Ok((addr, async move { Ok(server.await?) }))
+    }
+
+    async fn handle(self: Arc<Self>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let res = match self.forward(req).await {
+            Ok(res) => res,
+            Err(e) => {
+                let mut res = Response::new(Body::from(format!("{:#}", e)));
+                *res.status_mut() = StatusCode::BAD_GATEWAY;
+                res
+            }
+        };
+        Ok(res)
+    }
+
+    async fn forward(self: &Arc<Self>, req: Request<Body>) -> Result<Response<Body>> {
+        let (parts, body) = req.into_parts();
+        let mirrored = Mirrored {
+            method: parts.method,
+            path: parts
+                .uri
+                .path_and_query()
+                .map(|v| v.as_str())
+                .unwrap_or("/")
+                .to_string(),
+            headers: strip_hop_headers(&parts.headers),
+            body: hyper::body::to_bytes(body).await?,
+        };
+        let primary = mirrored.send(&self.client, &self.primary).await?;
+
+        let proxy = self.clone();
+        let captured = primary.clone();
+        tokio::spawn(async move {
+            let title = format!("{} {}", mirrored.method, mirrored.path);
+            let result = proxy.compare(&mirrored, captured).await;
+            let text = match result {
+                Ok(diff) if diff.is_empty() => return,
+                Ok(diff) => format!("[{}] diff\n{}", title, diff),
+                Err(e) => format!("[{}] error: {:#}\n", title, e),
+            };
+            if let Err(e) = proxy.write_report(&text) {
+                eprintln!("write report error: {:#}", e);
+            }
+        });
+
+        let mut res = Response::new(Body::from(primary.body));
+        *res.status_mut() = primary.status;
+        *res.headers_mut() = strip_hop_headers(&primary.headers);
+        Ok(res)
+    }
+
+    async fn compare(&self, mirrored: &Mirrored, primary: Captured) -> Result<String> {
+        let candidate = mirrored.send(&self.client, &self.candidate).await?;
+        primary
+            .into_response()?
+            .diff(candidate.into_response()?, &self.response)
+            .await
+    }
+
+    fn write_report(&self, text: &str) -> Result<()> {
+        match self.report.as_ref() {
+            Some(path) => {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                file.write_all(console::strip_ansi_codes(text).as_bytes())?;
+            }
+            None => write!(std::io::stdout().lock(), "{}", text)?,
+        }
+        Ok(())
+    }
+}
+
+impl Mirrored {
+    async fn send(&self, client: &reqwest::Client, target: &Url) -> Result<Captured> {
+        let base = target.as_str().trim_end_matches('/');
+        let url = Url::parse(&format!("{}/{}", base, self.path.trim_start_matches('/')))?;
+        let res = client
+            .request(self.method.clone(), url)
+            .headers(self.headers.clone())
+            .body(self.body.clone())
+            .send()
+            .await?;
+        Ok(Captured {
+            status: res.status(),
+            headers: res.headers().clone(),
+            body: res.bytes().await?,
+        })
+    }
+}
+
+impl Captured {
+    fn into_response(self) -> Result<ResponseExt> {
+        let mut builder = http::Response::builder().status(self.status);
+        for (k, v) in &self.headers {
+            builder = builder.header(k, v);
+        }
+        let res: reqwest::Response = builder.body(self.body)?.into();
+        Ok(res.into())
+    }
+}
+
+fn strip_hop_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut headers = headers.clone();
+    for name in HOP_HEADERS {
+        headers.remove(*name);
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::mock;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn t1() {
+        let _m1 = mock("GET", "/primary/todo?id=1")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id": 1, "title": "a"}"#)
+            .create();
+        let _m2 = mock("GET", "/candidate/todo?id=1")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id": 1, "title": "b"}"#)
+            .create();
+        let report = std::env::temp_dir().join(format!("xdiff-proxy-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&report);
+        let base = Url::parse(&mockito::server_url()).unwrap();
+        let proxy = Proxy::new(
+            base.join("/primary").unwrap(),
+            base.join("/candidate").unwrap(),
+            ResponseProfile::new(vec!["date".to_string()], vec![]),
+            Some(report.to_string_lossy().to_string()),
+        )
+        .unwrap();
+        let (addr, server) = proxy.bind(([127, 0, 0, 1], 0).into()).unwrap();
+        tokio::spawn(server);
+
+        let res = reqwest::get(format!("http://{}/todo?id=1", addr))
+            .await
+            .unwrap();
+        assert_eq!(res.text().await.unwrap(), r#"{"id": 1, "title": "a"}"#);
+
+        let mut content = String::new();
+        for _ in 0..50 {
+            content = std::fs::read_to_string(&report).unwrap_or_default();
+            if !content.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert!(content.starts_with("[GET /todo?id=1] diff\n"));
+        assert!(content.contains(r#"-  "title": "a""#));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{ExtraArgs, RequestProfile, ResponseProfile};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use reqwest::{
+    header::{self, HeaderMap, HeaderName},
+    Method,
+};
+use serde_json::Value;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use url::Url;
+
+/// Format of an access log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// nginx `main`, the combined format followed by extra quoted fields
+    Nginx,
+    /// Apache/nginx combined format
+    Combined,
+    /// one json object per line with `method`, `path` and optional `query` and `headers`
+    Jsonl,
+}
+
+/// A request read from an access log
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub method: Method,
+    /// path with the query string, like `/todos?id=1`
+    pub path: String,
+    pub headers: HeaderMap,
+}
+
+/// Replays logged requests against two targets and reports the differing ones
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub target1: Url,
+    pub target2: Url,
+    /// only these methods are replayed
+    pub methods: Vec<Method>,
+    /// fraction of the matching requests to replay, evenly spread
+    pub sample: f64,
+    /// max requests per second for each target
+    pub rate: Option<f64>,
+    pub response: ResponseProfile,
+}
+
+fn combined_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"^\S+ \S+ \S+ \[[^\]]*\] "(\S+) (\S+)[^"]*" \d{3} \S+(?: "([^"]*)" "([^"]*)")?"#,
+        )
+        .unwrap()
+    })
+}
+
+impl LogEntry {
+    /// Parse a log line, `None` for blank lines
+    pub fn parse(line: &str, format: LogFormat) -> Result<Option<Self>> {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        let entry = match format {
+            LogFormat::Nginx | LogFormat::Combined => {
+                let caps = combined_regex()
+                    .captures(line)
+                    .ok_or_else(|| anyhow!("invalid log line: {}", line))?;
+                let mut headers = HeaderMap::new();
+                let optional = [
+                    (header::REFERER, caps.get(3)),
+                    (header::USER_AGENT, caps.get(4)),
+                ];
+                for (name, value) in optional {
+                    match value.map(|v| v.as_str()) {
+                        Some(v) if v != "-" && !v.is_empty() => {
+                            headers.insert(name, v.parse()?);
+                        }
+                        _ => {}
+                    }
+                }
+                Self {
+                    method: caps[1].parse()?,
+                    path: caps[2].to_string(),
+                    headers,
+                }
+            }
+            LogFormat::Jsonl => {
+                let value: Value = serde_json::from_str(line)?;
+                let text = |keys: &[&str]| {
+                    keys.iter()
+                        .find_map(|k| value.get(*k).and_then(|v| v.as_str()))
+                        .map(|v| v.to_string())
+                };
+                let mut path = text(&["path", "uri", "request_uri"])
+                    .ok_or_else(|| anyhow!("missing path in log line: {}", line))?;
+                if let Some(query) = text(&["query", "args"]).filter(|q| !q.is_empty()) {
+                    path = format!("{}?{}", path, query);
+                }
+                let mut headers = HeaderMap::new();
+                if let Some(obj) = value.get("headers").and_then(|v| v.as_object()) {
+                    for (k, v) in obj {
+                        if let Some(v) = v.as_str() {
+                            headers.insert(HeaderName::from_str(k)?, v.parse()?);
+                        }
+                    }
+                }
+                Self {
+                    method: text(&["method"])
+                        .unwrap_or_else(|| "GET".to_string())
+                        .parse()?,
+                    path,
+                    headers,
+                }
+            }
+        };
+        Ok(Some(entry))
+    }
+
+    /// The request against `target`, a path prefix of the target is kept
+    fn profile(&self, target: &Url) -> Result<RequestProfile> {
+        let base = target.as_str().trim_end_matches('/');
+        let url = Url::parse(&format!("{}/{}", base, self.path.trim_start_matches('/')))?;
+        Ok(RequestProfile::new(
+            self.method.clone(),
+            url,
+            None,
+            self.headers.clone(),
+            None,
+        ))
+    }
+}
+
+impl Replay {
+    /// Entries with an allowed method, sampled
+    pub fn select(&self, entries: Vec<LogEntry>) -> Vec<LogEntry> {
+        let entries = entries
+            .into_iter()
+            .filter(|e| self.methods.contains(&e.method));
+        let sample = self.sample.clamp(0.0, 1.0);
+        entries
+            .enumerate()
+            .filter(|(idx, _)| {
+                (*idx as f64 * sample).floor() != ((*idx + 1) as f64 * sample).floor()
+            })
+            .map(|(_, e)| e)
+            .collect()
+    }
+
+    /// Replay the entries, print the diff of each differing request and a summary
+    pub async fn run(&self, entries: Vec<LogEntry>) -> Result<String> {
+        let entries = self.select(entries);
+        let interval = self.rate.map(|r| Duration::from_secs_f64(1.0 / r));
+        let args = ExtraArgs::default();
+        let stdout = std::io::stdout();
+        let (mut same, mut diff, mut error) = (0, 0, 0);
+        for entry in &entries {
+            let started = tokio::time::Instant::now();
+            let title = format!("{} {}", entry.method, entry.path);
+            let result = async {
+                let r1 = entry.profile(&self.target1)?.send(&args).await?;
+                let r2 = entry.profile(&self.target2)?.send(&args).await?;
+                r1.diff(r2, &self.response).await
+            }
+            .await;
+            let mut stdout = stdout.lock();
+            match result {
+                Ok(output) if output.is_empty() => same += 1,
+                Ok(output) => {
+                    diff += 1;
+                    writeln!(stdout, "[{}] diff", title)?;
+                    write!(stdout, "{}", output)?;
+                }
+                Err(e) => {
+                    error += 1;
+                    writeln!(stdout, "[{}] error: {:#}", title, e)?;
+                }
+            }
+            drop(stdout);
+            if let Some(interval) = interval {
+                tokio::time::sleep_until(started + interval).await;
+            }
+        }
+        writeln!(
+            stdout.lock(),
+            "{} requests: {} same, {} diff, {} error",
+            entries.len(),
+            same,
+            diff,
+            error
+        )?;
+        if diff + error > 0 {
+            return Err(anyhow!("{} requests differ or failed", diff + error));
+        }
+        Ok("".to_string())
+    }
+}
+
+/// Parse every line of a log, skipping the lines that can't be parsed
+pub fn parse_log(content: &str, format: LogFormat) -> (Vec<LogEntry>, usize) {
+    let mut skipped = 0;
+    let entries = content
+        .lines()
+        .filter_map(|line| match LogEntry::parse(line, format) {
+            Ok(entry) => entry,
+            Err(_) => {
+                skipped += 1;
+                None
+            }
+        })
+        .collect();
+    (entries, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let line = r#"127.0.0.1 - - [10/Oct/2022:13:55:36 +0000] "GET /todos?id=1 HTTP/1.1" 200 2326 "-" "curl/7.79" "-""#;
+        let entry = LogEntry::parse(line, LogFormat::Nginx).unwrap().unwrap();
+        assert_eq!(entry.method, Method::GET);
+        assert_eq!(entry.path, "/todos?id=1");
+        assert_eq!(entry.headers[header::USER_AGENT], "curl/7.79");
+        assert!(!entry.headers.contains_key(header::REFERER));
+
+        let line =
+            r#"{"method": "POST", "path": "/todos", "query": "a=1", "headers": {"x-id": "7"}}"#;
+        let entry = LogEntry::parse(line, LogFormat::Jsonl).unwrap().unwrap();
+        assert_eq!(entry.method, Method::POST);
+        assert_eq!(entry.path, "/todos?a=1");
+        assert_eq!(entry.headers["x-id"], "7");
+        let url = Url::parse("http://b:8080/api/").unwrap();
+        assert_eq!(
+            entry.profile(&url).unwrap().url.as_str(),
+            "http://b:8080/api/todos?a=1"
+        );
+
+        let (entries, skipped) = parse_log("oops\n\n", LogFormat::Combined);
+        assert_eq!((entries.len(), skipped), (0, 1));
+    }
+
+    #[test]
+    fn t2() {
+        let entry = |method: Method| LogEntry {
+            method,
+            path: "/".to_string(),
+            headers: HeaderMap::new(),
+        };
+        let mut entries: Vec<_> = (0..10).map(|_| entry(Method::GET)).collect();
+        entries.push(entry(Method::DELETE));
+        let replay = Replay {
+            target1: Url::parse("http://a").unwrap(),
+            target2: Url::parse("http://b").unwrap(),
+            methods: vec![Method::GET],
+            sample: 0.5,
+            rate: None,
+            response: ResponseProfile::default(),
+        };
+        let selected = replay.select(entries);
+        assert_eq!(selected.len(), 5);
+        assert!(selected.iter().all(|e| e.method == Method::GET));
+    }
+
+    #[tokio::test]
+    async fn t3() {
+        let _m1 = mockito::mock("GET", "/replay1/todos")
+            .with_body("a")
+            .create();
+        let _m2 = mockito::mock("GET", "/replay2/todos")
+            .with_body("b")
+            .create();
+        let target =
+            |path: &str| Url::parse(&format!("{}{}", mockito::server_url(), path)).unwrap();
+        let entry = LogEntry {
+            method: Method::GET,
+            path: "/todos".to_string(),
+            headers: HeaderMap::new(),
+        };
+        let mut replay = Replay {
+            target1: target("/replay1"),
+            target2: target("/replay1"),
+            methods: vec![Method::GET],
+            sample: 1.0,
+            rate: None,
+            response: ResponseProfile::default(),
+        };
+        assert!(replay.run(vec![entry.clone()]).await.is_ok());
+        replay.target2 = target("/replay2");
+        assert!(replay.run(vec![entry]).await.is_err());
+    }
+}
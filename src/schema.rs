@@ -0,0 +1,473 @@
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Keywords `check` validates
+const KEYWORDS: &[&str] = &[
+    "$ref",
+    "type",
+    "nullable",
+    "enum",
+    "const",
+    "required",
+    "properties",
+    "additionalProperties",
+    "minItems",
+    "maxItems",
+    "items",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+    "uniqueItems",
+    "minProperties",
+    "maxProperties",
+    "allOf",
+    "anyOf",
+    "oneOf",
+];
+
+/// Keywords that don't constrain a value, `definitions` are only reached by `$ref`,
+/// `format` is an annotation by default in JSON Schema
+const ANNOTATIONS: &[&str] = &[
+    "format",
+    "$schema",
+    "$id",
+    "$comment",
+    "$defs",
+    "definitions",
+    "title",
+    "description",
+    "default",
+    "example",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+    "externalDocs",
+    "xml",
+    "discriminator",
+];
+
+/// A JSON Schema, or an OpenAPI schema object, loaded from `file.yml` or `file.yml#/json/pointer`.
+/// Local `$ref`s are resolved against the whole file, keywords that aren't checked are errors
+#[derive(Debug, Clone)]
+pub struct Schema {
+    root: Value,
+    pointer: String,
+}
+
+impl Schema {
+    pub fn load(spec: &str) -> Result<Self> {
+        let (path, pointer) = spec.split_once('#').unwrap_or((spec, ""));
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("read schema file {} error: {}", path, e))?;
+        Self::new(serde_yaml::from_str(&content)?, pointer)
+    }
+
+    /// The schema of a spec, loaded once per process
+    pub fn load_cached(spec: &str) -> Result<Arc<Self>> {
+        static CACHE: OnceLock<Mutex<HashMap<String, Arc<Schema>>>> = OnceLock::new();
+        let cache = CACHE.get_or_init(Default::default);
+        if let Some(schema) = cache.lock().unwrap().get(spec) {
+            return Ok(schema.clone());
+        }
+        let schema = Arc::new(Self::load(spec)?);
+        cache
+            .lock()
+            .unwrap()
+            .insert(spec.to_string(), schema.clone());
+        Ok(schema)
+    }
+
+    fn new(root: Value, pointer: &str) -> Result<Self> {
+        let schema = Self {
+            root,
+            pointer: pointer.to_string(),
+        };
+        let at = format!("#{}", pointer);
+        schema.check_keywords(schema.lookup(&at)?, &at, &mut vec![])?;
+        Ok(schema)
+    }
+
+    /// Violations of the value, like `$.items[0].id: expected integer, got string`
+    pub fn validate(&self, value: &Value) -> Result<Vec<String>> {
+        let schema = self.lookup(&format!("#{}", self.pointer))?;
+        let mut errors = vec![];
+        self.check(schema, value, "$", &mut errors)?;
+        Ok(errors)
+    }
+
+    /// The schema a `$ref` points to, or the schema itself
+    fn resolve<'a>(&'a self, schema: &'a Value) -> Result<&'a Value> {
+        match schema.get("$ref").and_then(|v| v.as_str()) {
+            Some(r) => self.resolve(self.lookup(r)?),
+            None => Ok(schema),
+        }
+    }
+
+    /// The value at a local ref like `#/components/schemas/Todo`
+    fn lookup(&self, r: &str) -> Result<&Value> {
+        let pointer = r
+            .strip_prefix('#')
+            .ok_or_else(|| anyhow!("only local refs are supported: {}", r))?;
+        self.root
+            .pointer(pointer)
+            .ok_or_else(|| anyhow!("unresolved schema ref: {}", r))
+    }
+
+    /// Error on keywords `check` would ignore, so a body never passes by omission
+    fn check_keywords(&self, schema: &Value, at: &str, refs: &mut Vec<String>) -> Result<()> {
+        let obj = schema
+            .as_object()
+            .ok_or_else(|| anyhow!("unsupported schema at {}, only objects are", at))?;
+        let has_ref = obj.contains_key("$ref");
+        for (k, v) in obj {
+            if k.starts_with("x-") || ANNOTATIONS.contains(&k.as_str()) {
+                continue;
+            }
+            if !KEYWORDS.contains(&k.as_str()) {
+                return Err(anyhow!("unsupported schema keyword {} at {}", k, at));
+            }
+            let child = format!("{}/{}", at, k);
+            match (k.as_str(), v) {
+                ("$ref", Value::String(r)) if !refs.contains(r) => {
+                    refs.push(r.clone());
+                    self.check_keywords(self.lookup(r)?, r, refs)?;
+                }
+                ("$ref", Value::String(_)) => {}
+                ("$ref", _) => return Err(anyhow!("invalid $ref at {}", at)),
+                (_, _) if has_ref => {
+                    return Err(anyhow!("keyword {} next to $ref is ignored at {}", k, at))
+                }
+                ("properties", Value::Object(props)) => {
+                    for (name, prop) in props {
+                        self.check_keywords(prop, &format!("{}/{}", child, name), refs)?;
+                    }
+                }
+                ("additionalProperties", Value::Object(_)) => {
+                    self.check_keywords(v, &child, refs)?
+                }
+                ("items", Value::Object(_)) => self.check_keywords(v, &child, refs)?,
+                ("items", _) => {
+                    return Err(anyhow!(
+                        "unsupported schema at {}, only one items schema is",
+                        child
+                    ))
+                }
+                ("allOf" | "anyOf" | "oneOf", Value::Array(subs)) => {
+                    for (i, sub) in subs.iter().enumerate() {
+                        self.check_keywords(sub, &format!("{}/{}", child, i), refs)?;
+                    }
+                }
+                ("pattern", Value::String(pattern)) => {
+                    Regex::new(pattern).with_context(|| format!("invalid pattern at {}", at))?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn check(
+        &self,
+        schema: &Value,
+        value: &Value,
+        path: &str,
+        errors: &mut Vec<String>,
+    ) -> Result<()> {
+        let schema = self.resolve(schema)?;
+        if value.is_null() && schema.get("nullable").and_then(|v| v.as_bool()) == Some(true) {
+            return Ok(());
+        }
+        let mut error = |msg: String| errors.push(format!("{}: {}", path, msg));
+
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::Array(v) => v.iter().filter_map(|v| v.as_str()).collect(),
+                v => v.as_str().into_iter().collect(),
+            };
+            if !types.iter().any(|t| type_matches(t, value)) {
+                error(format!(
+                    "expected {}, got {}",
+                    types.join(" or "),
+                    type_name(value)
+                ));
+                return Ok(());
+            }
+        }
+        if let Some(values) = schema.get("enum").and_then(|v| v.as_array()) {
+            if !values.contains(value) {
+                error(format!(
+                    "{} is not one of {}",
+                    value,
+                    Value::Array(values.clone())
+                ));
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                error(format!("expected {}, got {}", expected, value));
+            }
+        }
+
+        match value {
+            Value::Object(obj) => {
+                if let Some(required) = schema.get("required").and_then(|v| v.as_array()) {
+                    for key in required.iter().filter_map(|v| v.as_str()) {
+                        if !obj.contains_key(key) {
+                            error(format!("missing required property {}", key));
+                        }
+                    }
+                }
+                let len = obj.len() as u64;
+                if let Some(min) = schema.get("minProperties").and_then(|v| v.as_u64()) {
+                    if len < min {
+                        error(format!("expected at least {} properties, got {}", min, len));
+                    }
+                }
+                if let Some(max) = schema.get("maxProperties").and_then(|v| v.as_u64()) {
+                    if len > max {
+                        error(format!("expected at most {} properties, got {}", max, len));
+                    }
+                }
+                let props = schema.get("properties").and_then(|v| v.as_object());
+                for (k, v) in obj {
+                    let child = format!("{}.{}", path, k);
+                    match (
+                        props.and_then(|p| p.get(k)),
+                        schema.get("additionalProperties"),
+                    ) {
+                        (Some(prop), _) => self.check(prop, v, &child, errors)?,
+                        (None, Some(Value::Bool(false))) => {
+                            errors.push(format!("{}: unexpected property", child))
+                        }
+                        (None, Some(extra)) if extra.is_object() => {
+                            self.check(extra, v, &child, errors)?
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Value::Array(arr) => {
+                let len = arr.len() as u64;
+                if let Some(min) = schema.get("minItems").and_then(|v| v.as_u64()) {
+                    if len < min {
+                        error(format!("expected at least {} items, got {}", min, len));
+                    }
+                }
+                if let Some(max) = schema.get("maxItems").and_then(|v| v.as_u64()) {
+                    if len > max {
+                        error(format!("expected at most {} items, got {}", max, len));
+                    }
+                }
+                if schema.get("uniqueItems") == Some(&Value::Bool(true))
+                    && arr.iter().enumerate().any(|(i, v)| arr[..i].contains(v))
+                {
+                    error("expected unique items".to_string());
+                }
+                if let Some(items) = schema.get("items").filter(|v| v.is_object()) {
+                    for (i, v) in arr.iter().enumerate() {
+                        self.check(items, v, &format!("{}[{}]", path, i), errors)?;
+                    }
+                }
+            }
+            Value::String(s) => {
+                let len = s.chars().count() as u64;
+                if let Some(min) = schema.get("minLength").and_then(|v| v.as_u64()) {
+                    if len < min {
+                        error(format!("expected at least {} chars, got {}", min, len));
+                    }
+                }
+                if let Some(max) = schema.get("maxLength").and_then(|v| v.as_u64()) {
+                    if len > max {
+                        error(format!("expected at most {} chars, got {}", max, len));
+                    }
+                }
+                if let Some(pattern) = schema.get("pattern").and_then(|v| v.as_str()) {
+                    if !Regex::new(pattern)?.is_match(s) {
+                        error(format!("{:?} doesn't match {}", s, pattern));
+                    }
+                }
+            }
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+                // OpenAPI 3.0 makes `minimum` exclusive with a boolean, JSON Schema has a number
+                let exclusive = |key| schema.get(key) == Some(&Value::Bool(true));
+                if let Some(min) = schema.get("minimum").and_then(|v| v.as_f64()) {
+                    if n < min || (n == min && exclusive("exclusiveMinimum")) {
+                        error(format!("{} is less than {}", n, min));
+                    }
+                }
+                if let Some(min) = schema.get("exclusiveMinimum").and_then(|v| v.as_f64()) {
+                    if n <= min {
+                        error(format!("{} is not greater than {}", n, min));
+                    }
+                }
+                if let Some(max) = schema.get("maximum").and_then(|v| v.as_f64()) {
+                    if n > max || (n == max && exclusive("exclusiveMaximum")) {
+                        error(format!("{} is greater than {}", n, max));
+                    }
+                }
+                if let Some(max) = schema.get("exclusiveMaximum").and_then(|v| v.as_f64()) {
+                    if n >= max {
+                        error(format!("{} is not less than {}", n, max));
+                    }
+                }
+                if let Some(m) = schema.get("multipleOf").and_then(|v| v.as_f64()) {
+                    let q = n / m;
+                    if m <= 0.0 || (q - q.round()).abs() > 1e-9 {
+                        error(format!("{} is not a multiple of {}", n, m));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(all) = schema.get("allOf").and_then(|v| v.as_array()) {
+            for sub in all {
+                self.check(sub, value, path, errors)?;
+            }
+        }
+        for (key, want_one) in [("anyOf", false), ("oneOf", true)] {
+            if let Some(subs) = schema.get(key).and_then(|v| v.as_array()) {
+                let mut matched = 0;
+                for sub in subs {
+                    let mut sub_errors = vec![];
+                    self.check(sub, value, path, &mut sub_errors)?;
+                    if sub_errors.is_empty() {
+                        matched += 1;
+                    }
+                }
+                if matched == 0 || (want_one && matched > 1) {
+                    errors.push(format!("{}: {} matched {} schemas", path, key, matched));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn type_matches(t: &str, value: &Value) -> bool {
+    match t {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        t => t == type_name(value),
+    }
+}
+
+pub(crate) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let schema = Schema::load("fixtures/todo.openapi.yml#/components/schemas/Todo").unwrap();
+        let todo = json!({"id": 1, "title": "a", "completed": false});
+        assert!(schema.validate(&todo).unwrap().is_empty());
+        let todo = json!({"id": "1", "title": "a"});
+        assert_eq!(
+            schema.validate(&todo).unwrap(),
+            vec![
+                "$: missing required property completed",
+                "$.id: expected integer, got string",
+            ]
+        );
+
+        let schema = Schema::load(
+            "fixtures/todo.openapi.yml#/paths/~1todos/get/responses/200/content/application~1json/schema",
+        )
+        .unwrap();
+        let todos = json!([{"id": 1, "title": "a", "completed": false}, {"id": 2.5, "title": "b", "completed": true}]);
+        assert_eq!(
+            schema.validate(&todos).unwrap(),
+            vec!["$[1].id: expected integer, got number"]
+        );
+        assert!(Schema::load("fixtures/todo.openapi.yml#/missing").is_err());
+    }
+
+    #[test]
+    fn t2() {
+        let schema = Schema::new(json!({"type": "integer", "x-note": "a"}), "").unwrap();
+        assert!(schema.validate(&json!(1.0)).unwrap().is_empty());
+        assert_eq!(
+            schema.validate(&json!(1.5)).unwrap(),
+            vec!["$: expected integer, got number"]
+        );
+
+        let root = json!({
+            "definitions": {"Id": {"type": "integer", "not": {"const": 0}}},
+            "properties": {"id": {"$ref": "#/definitions/Id"}}
+        });
+        let err = Schema::new(root, "").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported schema keyword not at #/definitions/Id"
+        );
+        for schema in [
+            json!({"items": {"contains": {"type": "string"}}}),
+            json!({"anyOf": [{"patternProperties": {"^x": {}}}]}),
+            json!({"$ref": "other.yml#/Todo"}),
+            json!({"$ref": "#/definitions/Id", "type": "object", "definitions": {"Id": {}}}),
+            json!({"pattern": "("}),
+            json!({"items": [{"type": "string"}]}),
+        ] {
+            assert!(Schema::new(schema.clone(), "").is_err(), "{}", schema);
+        }
+    }
+
+    #[test]
+    fn t3() {
+        let schema = Schema::new(
+            json!({
+                "type": "object",
+                "minProperties": 2,
+                "properties": {
+                    "price": {"type": "number", "format": "double", "minimum": 0, "exclusiveMinimum": true, "multipleOf": 0.5},
+                    "rank": {"type": "integer", "exclusiveMaximum": 10},
+                    "tags": {"type": "array", "uniqueItems": true}
+                }
+            }),
+            "",
+        )
+        .unwrap();
+        let value = json!({"price": 1.5, "rank": 9, "tags": ["a", "b"]});
+        assert!(schema.validate(&value).unwrap().is_empty());
+        let value = json!({"price": 0, "rank": 10, "tags": ["a", "a"]});
+        assert_eq!(
+            schema.validate(&value).unwrap(),
+            vec![
+                "$.price: 0 is less than 0",
+                "$.rank: 10 is not less than 10",
+                "$.tags: expected unique items",
+            ]
+        );
+        assert_eq!(
+            schema.validate(&json!({"price": 1.2})).unwrap(),
+            vec![
+                "$: expected at least 2 properties, got 1",
+                "$.price: 1.2 is not a multiple of 0.5",
+            ]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::{anyhow, Result};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+use url::Url;
+
+/// Values referenced as `{{name}}` in urls, headers, params and body
+pub type Variables = HashMap<String, Value>;
+
+/// Characters left as-is when a variable is substituted into an url
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+fn var_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    // spaces inside the braces of an url template arrive percent-encoded
+    RE.get_or_init(|| Regex::new(r"\{\{(?:\s|%20)*([A-Za-z0-9_.\-]+)(?:\s|%20)*\}\}").unwrap())
+}
+
+fn path_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    // `{{name}}` variables are matched too, so they are left for `render_url`
+    RE.get_or_init(|| Regex::new(r"\{\{[^}]*\}\}|\{([A-Za-z_][A-Za-z0-9_\-]*)\}").unwrap())
+}
+
+fn lookup<'a>(vars: &'a Variables, name: &str) -> Result<&'a Value> {
+    vars.get(name)
+        .ok_or_else(|| anyhow!("undefined variable: {}", name))
+}
+
+pub fn value_to_string(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+fn render_with(s: &str, vars: &Variables, encode: impl Fn(String) -> String) -> Result<String> {
+    let mut err = None;
+    let output = var_regex().replace_all(s, |caps: &Captures| match lookup(vars, &caps[1]) {
+        Ok(v) => encode(value_to_string(v)),
+        Err(e) => {
+            err.get_or_insert(e);
+            String::new()
+        }
+    });
+    match err {
+        Some(e) => Err(e),
+        None => Ok(output.into_owned()),
+    }
+}
+
+pub fn render_str(s: &str, vars: &Variables) -> Result<String> {
+    render_with(s, vars, |v| v)
+}
+
+/// Render a shell command, substituted values are quoted so they stay single words
+pub fn render_shell(s: &str, vars: &Variables) -> Result<String> {
+    render_with(s, vars, |v| shell_words::quote(&v).into_owned())
+}
+
+/// Substitute the defined variables and leave the others in place, returning the undefined names
+pub fn render_partial(s: &str, vars: &Variables) -> (String, Vec<String>) {
+    let mut missing = vec![];
+    let output = var_regex().replace_all(s, |caps: &Captures| match lookup(vars, &caps[1]) {
+        Ok(v) => value_to_string(v),
+        Err(_) => {
+            missing.push(caps[1].to_string());
+            caps[0].to_string()
+        }
+    });
+    (output.into_owned(), missing)
+}
+
+/// Render an url template, substituted values are percent-encoded
+pub fn render_url(url: &Url, vars: &Variables) -> Result<Url> {
+    // the url parser encodes braces in the path, decode them to find the placeholders
+    let s = url.as_str().replace("%7B", "{").replace("%7D", "}");
+    let s = render_with(&s, vars, |v| utf8_percent_encode(&v, COMPONENT).to_string())?;
+    Ok(Url::parse(&s)?)
+}
+
+/// Fill `{name}` path parameters of an url, substituted values are percent-encoded
+pub fn render_path(url: &Url, params: &BTreeMap<String, String>) -> Result<Url> {
+    let path = url.path().replace("%7B", "{").replace("%7D", "}");
+    let mut err = None;
+    let path = path_regex().replace_all(&path, |caps: &Captures| match caps.get(1) {
+        Some(name) => match params.get(name.as_str()) {
+            Some(v) => utf8_percent_encode(v, COMPONENT).to_string(),
+            None => {
+                err.get_or_insert_with(|| anyhow!("missing path param: {}", name.as_str()));
+                String::new()
+            }
+        },
+        None => caps[0].to_string(),
+    });
+    if let Some(e) = err {
+        return Err(e);
+    }
+    let mut url = url.clone();
+    url.set_path(&path);
+    Ok(url)
+}
+
+/// Render string values of a json tree, a string that is a single `{{name}}` keeps the variable type
+pub fn render_json(value: &Value, vars: &Variables) -> Result<Value> {
+    Ok(match value {
+        Value::String(s) => match var_regex().captures(s) {
+            Some(caps) if caps[0].len() == s.len() => lookup(vars, &caps[1])?.clone(),
+            _ => Value::String(render_str(s, vars)?),
+        },
+        Value::Array(arr) => Value::Array(
+            arr.iter()
+                .map(|v| render_json(v, vars))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(k, v)| Ok((k.clone(), render_json(v, vars)?)))
+                .collect::<Result<_>>()?,
+        ),
+        v => v.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn t1() {
+        let vars: Variables = [
+            ("id".to_string(), json!(42)),
+            ("name".to_string(), json!("a b/c")),
+        ]
+        .into_iter()
+        .collect();
+        let url = Url::parse("https://example.com/todos/{{id}}?q={{ name }}").unwrap();
+        assert_eq!(
+            render_url(&url, &vars).unwrap().as_str(),
+            "https://example.com/todos/42?q=a%20b%2Fc"
+        );
+        assert_eq!(
+            render_json(&json!({"id": "{{id}}", "title": "todo {{id}}"}), &vars).unwrap(),
+            json!({"id": 42, "title": "todo 42"})
+        );
+        assert!(render_str("{{missing}}", &vars).is_err());
+    }
+
+    #[test]
+    fn t2() {
+        let url =
+            Url::parse("https://example.com/users/{id}/orders/{orderId}/{{v}}?a={b}").unwrap();
+        let params = [
+            ("id".to_string(), "a b/c".to_string()),
+            ("orderId".to_string(), "7".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            render_path(&url, &params).unwrap().as_str(),
+            "https://example.com/users/a%20b%2Fc/orders/7/%7B%7Bv%7D%7D?a={b}"
+        );
+        assert!(render_path(&url, &BTreeMap::new()).is_err());
+    }
+}